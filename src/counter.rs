@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use crate::vocabulary::WordId;
+
+/// Counts the n-grams of every order up to a fixed one, one sentence at a time.
+///
+/// Every n-gram is incremented in place in one map per order, so the corpus
+/// never has to be held in memory as a whole.
+#[derive(Clone, Debug)]
+pub struct NgramCounter {
+    // ngrams[n - 1] holds the counts of all n-grams
+    ngrams: Vec<HashMap<Vec<WordId>, u32>>,
+}
+
+impl NgramCounter {
+    pub fn new(order: usize) -> NgramCounter {
+        NgramCounter {
+            ngrams: vec![HashMap::new(); order],
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.ngrams.len()
+    }
+
+    /// The number of distinct n-grams of every order.
+    pub fn len(&self) -> usize {
+        self.ngrams.iter().map(|counts| counts.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Count every n-gram of the sentence, n-grams never cross sentences.
+    pub fn add_sentence(&mut self, sentence: &[WordId]) {
+        for (n, counts) in (1..).zip(self.ngrams.iter_mut()) {
+            for gram in sentence.windows(n) {
+                // only allocate a key for n-grams that weren't seen yet
+                match counts.get_mut(gram) {
+                    Some(count) => *count += 1,
+                    None => {
+                        counts.insert(gram.to_vec(), 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add to the count of a single n-gram, without the shorter n-grams it is made of.
+    pub fn add(&mut self, gram: &[WordId], count: u32) {
+        let counts = &mut self.ngrams[gram.len() - 1];
+
+        match counts.get_mut(gram) {
+            Some(total) => *total += count,
+            None => {
+                counts.insert(gram.to_vec(), count);
+            }
+        }
+    }
+
+    /// Make sure the n-gram, and every shorter n-gram it is made of,
+    /// has a count, even if it was never seen.
+    pub fn add_unseen(&mut self, gram: &[WordId]) {
+        for (n, counts) in (1..).zip(self.ngrams.iter_mut()) {
+            for gram in gram.windows(n) {
+                if !counts.contains_key(gram) {
+                    counts.insert(gram.to_vec(), 0);
+                }
+            }
+        }
+    }
+
+    /// Add the counts of another counter, whose word IDs are mapped to the IDs of
+    /// this counter by `ids`, where `ids[other_id]` is the ID of `other_id`.
+    pub fn merge(&mut self, other: NgramCounter, ids: &[WordId]) {
+        for (counts, other_counts) in self.ngrams.iter_mut().zip(other.ngrams) {
+            for (gram, count) in other_counts {
+                let gram = gram.iter().map(|id| ids[*id as usize]).collect();
+
+                *counts.entry(gram).or_default() += count;
+            }
+        }
+    }
+
+    pub fn count(&self, gram: &[WordId]) -> u32 {
+        match gram.len() {
+            0 => 0,
+            n => self
+                .ngrams
+                .get(n - 1)
+                .and_then(|counts| counts.get(gram))
+                .copied()
+                .unwrap_or(0),
+        }
+    }
+
+    /// The counts of every order together, sorted by n-gram.
+    pub fn into_sorted(self) -> Vec<(Vec<WordId>, u32)> {
+        let mut counts = self.ngrams.into_iter().flatten().collect::<Vec<_>>();
+        counts.sort_unstable_by(|(l_gram, _), (r_gram, _)| l_gram.cmp(r_gram));
+
+        counts
+    }
+
+    /// The counts of every order, starting at unigrams.
+    pub fn into_counts(self) -> Vec<HashMap<Vec<WordId>, u32>> {
+        self.ngrams
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counter::*;
+
+    #[test]
+    fn test_add_sentence() {
+        let mut counter = NgramCounter::new(2);
+
+        counter.add_sentence(&[3, 4, 5]);
+        counter.add_sentence(&[3, 4, 6]);
+
+        assert_eq!(2, counter.count(&[3]));
+        assert_eq!(2, counter.count(&[3, 4]));
+        assert_eq!(1, counter.count(&[4, 6]));
+        // n-grams never cross sentences
+        assert_eq!(0, counter.count(&[5, 3]));
+        assert_eq!(0, counter.count(&[3, 4, 5]));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut counter = NgramCounter::new(2);
+        counter.add_sentence(&[3, 4]);
+
+        let mut other = NgramCounter::new(2);
+        other.add_sentence(&[3, 4, 5]);
+
+        // the IDs 3 and 4 are swapped in the other counter
+        counter.merge(other, &[0, 1, 2, 4, 3, 5]);
+
+        assert_eq!(1, counter.count(&[3, 4]));
+        assert_eq!(1, counter.count(&[4, 3]));
+        assert_eq!(1, counter.count(&[3, 5]));
+        assert_eq!(2, counter.count(&[3]));
+    }
+
+    #[test]
+    fn test_add_unseen() {
+        let mut counter = NgramCounter::new(2);
+
+        counter.add_sentence(&[3, 4]);
+        counter.add_unseen(&[4, 5]);
+
+        let counts = counter.into_counts();
+
+        assert_eq!(Some(&1), counts[0].get(&vec![4]));
+        assert_eq!(Some(&0), counts[0].get(&vec![5]));
+        assert_eq!(Some(&0), counts[1].get(&vec![4, 5]));
+    }
+}
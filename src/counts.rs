@@ -0,0 +1,769 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+use std::{mem, thread};
+
+use itertools::Itertools;
+
+use crate::counter::NgramCounter;
+use crate::filters::TokenFilter;
+use crate::normalization::Normalizer;
+use crate::sentences::SentenceSplitter;
+use crate::spill::Spills;
+use crate::table::{NgramTable, Storage, TableBuilder};
+use crate::tokenizer::{characters, Tokenizer};
+use crate::utils::{merge_hashmaps_with, pad_words, SENTENCE_END, SENTENCE_START, UNKNOWN};
+use crate::vocabulary::{Vocabulary, WordId, SENTENCE_END_ID, SENTENCE_START_ID, UNKNOWN_ID};
+use crate::Options;
+
+/// The counts of every n-gram of a corpus, from unigrams up to a fixed order.
+///
+/// They are gathered once, and every smoothing method derives its probabilities from them.
+/// Only the options about counting apply: sentence boundaries and the closed vocabulary.
+/// The n-grams are stored as arrays of word IDs of the vocabulary, in memory, or in sorted
+/// temporary files when they were spilled while counting with a memory limit.
+#[derive(Clone)]
+pub struct NgramCounts {
+    pub(crate) order: usize,
+    pub(crate) vocabulary: Vocabulary,
+    // ngrams[n - 1] holds the counts of all n-grams
+    pub(crate) ngrams: Vec<NgramTable<u32>>,
+    // where the tables of the counts, and of everything models derive from them, are kept
+    pub(crate) storage: Storage,
+    // counts_of_counts[n - 1] holds, for every count c, how many n-grams occur c times
+    pub(crate) counts_of_counts: Vec<HashMap<u32, u32>>,
+    pub(crate) total_words: u32,
+    pub(crate) vocabulary_size: u32,
+    // whether the vocabulary is closed, so that every other word was counted as <UNK>
+    closed_vocabulary: bool,
+    sentence_boundaries: bool,
+    // the corpus was split into words with this, and so are the sentences that are scored
+    preprocessing: Preprocessing,
+}
+
+impl NgramCounts {
+    /// Panics if the counts can't be spilled to temporary files when there is a memory limit.
+    pub fn new(
+        corpus: &[String],          // lines
+        vocabulary: &[Vec<String>], // optional extra n-grams to compute probabilities for
+        order: usize,
+        options: &Options,
+    ) -> NgramCounts {
+        assert!(order > 0, "The order of an n-gram model must be at least 1");
+
+        let preprocessing = Preprocessing::new(options);
+        let known_words = match options.closed_vocabulary() {
+            false => None,
+            true => Some(closed_vocabulary(
+                count_words(
+                    corpus,
+                    &|line| preprocessing.sentences(line),
+                    options.threads,
+                ),
+                vocabulary,
+                options,
+            )),
+        };
+
+        // with a memory limit the corpus is counted in batches, so that it can be spilled in between
+        let batch_size = match options.memory_limit {
+            None => corpus.len().max(1),
+            Some(_) => BATCH_LINES,
+        };
+
+        let mut counting = Counting::new(order, known_words, options);
+
+        corpus
+            .chunks(batch_size)
+            .try_for_each(|lines| counting.add_lines(lines))
+            .and_then(|_| counting.finish(vocabulary))
+            .expect("Failed to spill the counts to a temporary file")
+    }
+
+    /// Count a corpus file with a sentence on every line, which is read a batch of lines at a time.
+    ///
+    /// With a memory limit, the counts are spilled to temporary files and merged at the end,
+    /// so that files larger than the memory can be counted.
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        vocabulary: &[Vec<String>], // optional extra n-grams to compute probabilities for
+        order: usize,
+        options: &Options,
+    ) -> io::Result<NgramCounts> {
+        assert!(order > 0, "The order of an n-gram model must be at least 1");
+
+        let preprocessing = Preprocessing::new(options);
+
+        // a closed vocabulary takes an extra pass over the file to count the words
+        let known_words = match options.closed_vocabulary() {
+            false => None,
+            true => {
+                let word_counts = read_batches(File::open(&path)?).try_fold(
+                    HashMap::new(),
+                    |total, lines| -> io::Result<_> {
+                        let counts = count_words(
+                            &lines?,
+                            &|line| preprocessing.sentences(line),
+                            options.threads,
+                        );
+
+                        Ok(merge_hashmaps_with(counts, total, |l, r| l + r))
+                    },
+                )?;
+
+                Some(closed_vocabulary(word_counts, vocabulary, options))
+            }
+        };
+
+        let mut counting = Counting::new(order, known_words, options);
+
+        for lines in read_batches(File::open(&path)?) {
+            counting.add_lines(&lines?)?;
+        }
+
+        counting.finish(vocabulary)
+    }
+
+    fn from_ngrams(
+        order: usize,
+        vocabulary: Vocabulary,
+        ngrams: Vec<NgramTable<u32>>,
+        storage: Storage,
+        preprocessing: Preprocessing,
+        options: &Options,
+    ) -> NgramCounts {
+        let counts_of_counts = ngrams
+            .iter()
+            .map(|ngram_counts| {
+                ngram_counts
+                    .iter()
+                    .filter(|(gram, _)| !ends_with_sentence_start(gram))
+                    .fold(HashMap::new(), |mut counts, (_gram, count)| {
+                        *counts.entry(count).or_default() += 1;
+
+                        counts
+                    })
+            })
+            .collect_vec();
+
+        // the sentence start marker is only ever used as history,
+        // so it is not part of the words that probabilities are spread over
+        let sentence_starts = ngrams[0].get(&[SENTENCE_START_ID]).unwrap_or(0);
+        let total_words =
+            ngrams[0].iter().map(|(_gram, count)| count).sum::<u32>() - sentence_starts;
+        let vocabulary_size = ngrams[0]
+            .iter()
+            .filter(|(gram, _count)| !ends_with_sentence_start(gram))
+            .count() as u32;
+
+        NgramCounts {
+            order,
+            vocabulary,
+            ngrams,
+            storage,
+            counts_of_counts,
+            total_words,
+            vocabulary_size,
+            closed_vocabulary: options.closed_vocabulary(),
+            sentence_boundaries: options.sentence_boundaries,
+            preprocessing,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// The words the n-grams are made of, along with their IDs.
+    pub fn vocabulary(&self) -> &Vocabulary {
+        &self.vocabulary
+    }
+
+    /// Whether the word is part of the vocabulary, which a closed vocabulary
+    /// always is, since it maps unknown words to `<UNK>`.
+    pub fn knows(&self, word: &str) -> bool {
+        match self.closed_vocabulary {
+            true => true,
+            false => self
+                .vocabulary
+                .id(word)
+                .map(|id| self.ngrams[0].get(&[id]).is_some())
+                .unwrap_or(false),
+        }
+    }
+
+    /// How many times the n-gram was seen in the corpus.
+    pub fn count(&self, gram: &[String]) -> u32 {
+        self.count_ids(&self.vocabulary.ids(gram))
+    }
+
+    /// How many times the n-gram of word IDs was seen in the corpus.
+    pub fn count_ids(&self, gram: &[WordId]) -> u32 {
+        match gram.len() {
+            0 => self.total_words,
+            n if n <= self.order => self.ngrams[n - 1].get(gram).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Every known n-gram of the given order, along with its count,
+    /// which are read a block at a time when they were spilled to disk.
+    pub fn ngrams(&self, n: usize) -> impl Iterator<Item = (Vec<WordId>, u32)> + '_ {
+        self.ngrams[n - 1]
+            .iter()
+            .filter(|(gram, _count)| !ends_with_sentence_start(gram))
+    }
+
+    /// How many n-grams of every order the counts hold in memory, which is
+    /// only the first one of every block of n-grams once they were spilled.
+    pub fn ngrams_in_memory(&self) -> usize {
+        self.ngrams.iter().map(|ngrams| ngrams.in_memory()).sum()
+    }
+
+    /// The n-grams a sentence is scored by: every word along with
+    /// as many of the words before it as the order allows.
+    ///
+    /// When the corpus was split into sentences, so is the line, and every
+    /// sentence of it is scored on its own.
+    pub fn sentence_ngrams(&self, sentence: &str) -> Vec<Vec<String>> {
+        self.preprocessing
+            .sentences(sentence)
+            .into_iter()
+            // like when counting, empty sentences are skipped instead of scoring a lone </s>
+            .filter(|words| !words.is_empty())
+            .flat_map(|words| {
+                let (words, first) = match self.sentence_boundaries {
+                    false => (words, 0),
+                    // the start marker is never predicted itself
+                    true => (pad_words(words), 1),
+                };
+
+                (first..words.len())
+                    .map(|i| {
+                        let start = (i + 1).saturating_sub(self.order);
+
+                        words[start..=i].to_vec()
+                    })
+                    .collect_vec()
+            })
+            .collect_vec()
+    }
+}
+
+// How lines become the filtered words of their sentences, before they are counted
+#[derive(Clone)]
+struct Preprocessing {
+    splitter: Option<SentenceSplitter>,
+    tokenizer: Arc<dyn Tokenizer>,
+    normalizer: Normalizer,
+    filters: Vec<Arc<dyn TokenFilter>>,
+    // the word boundaries, when the filtered words are split into characters
+    characters: Option<bool>,
+}
+
+impl Preprocessing {
+    fn new(options: &Options) -> Self {
+        Preprocessing {
+            splitter: options.sentence_splitter.clone(),
+            tokenizer: options.tokenizer(),
+            normalizer: options.normalizer.clone(),
+            filters: options.filters.clone(),
+            characters: options.characters,
+        }
+    }
+
+    // the words of every sentence of the line, which is a single sentence without a splitter
+    fn sentences(&self, line: &str) -> Vec<Vec<String>> {
+        let words = |sentence: &str| {
+            let mut words = self.normalizer.words(&*self.tokenizer, sentence);
+            words.retain(|word| self.filters.iter().all(|filter| filter.keep(word)));
+
+            match self.characters {
+                None => words,
+                Some(word_boundaries) => characters(&words, word_boundaries),
+            }
+        };
+
+        match &self.splitter {
+            None => vec![words(line)],
+            Some(splitter) => splitter.split(line).into_iter().map(words).collect_vec(),
+        }
+    }
+}
+
+// How many lines are counted at a time when the counts may have to be spilled
+const BATCH_LINES: usize = 10_000;
+
+// Counts a corpus a batch of lines at a time into one counter, which is spilled
+// to a temporary file whenever it holds more n-grams than the memory limit
+struct Counting<'a> {
+    order: usize,
+    options: &'a Options,
+    preprocessing: Preprocessing,
+    known_words: Option<HashSet<String>>,
+    ids: Vocabulary,
+    counter: NgramCounter,
+    spills: Spills,
+}
+
+impl<'a> Counting<'a> {
+    fn new(order: usize, known_words: Option<HashSet<String>>, options: &'a Options) -> Self {
+        Counting {
+            order,
+            options,
+            preprocessing: Preprocessing::new(options),
+            known_words,
+            ids: Vocabulary::new(),
+            counter: NgramCounter::new(order),
+            spills: Spills::new(),
+        }
+    }
+
+    fn add_lines(&mut self, lines: &[String]) -> io::Result<()> {
+        let order = self.order;
+        let preprocessing = &self.preprocessing;
+        let sentences = |line: &str| preprocessing.sentences(line);
+        let known_words = self.known_words.as_ref();
+        let padding = self.options.sentence_boundaries;
+
+        match self.options.threads <= 1 {
+            true => count_lines(
+                lines,
+                &sentences,
+                known_words,
+                padding,
+                &mut self.ids,
+                &mut self.counter,
+            ),
+            // every shard of lines is counted with its own vocabulary, and merged in order,
+            // so the words get the same IDs as when the lines are counted one after another
+            false => {
+                let shards = in_shards(lines, self.options.threads, |lines| {
+                    let mut ids = Vocabulary::new();
+                    let mut counter = NgramCounter::new(order);
+                    count_lines(
+                        lines,
+                        &sentences,
+                        known_words,
+                        padding,
+                        &mut ids,
+                        &mut counter,
+                    );
+
+                    (ids, counter)
+                });
+
+                for (shard_ids, shard_counter) in shards {
+                    let shard_to_ids = self.ids.merge(&shard_ids);
+
+                    self.counter.merge(shard_counter, &shard_to_ids);
+                }
+            }
+        }
+
+        match self.options.memory_limit {
+            Some(limit) if self.counter.len() > limit => {
+                let counter = mem::replace(&mut self.counter, NgramCounter::new(order));
+
+                self.spills.spill(counter)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(mut self, vocabulary: &[Vec<String>]) -> io::Result<NgramCounts> {
+        let mut intern = |word: &str| match &self.known_words {
+            Some(known_words) if !known_words.contains(word) => UNKNOWN_ID,
+            _ => self.ids.intern(word),
+        };
+
+        // create initial 0 counts of the extra vocabulary, as well as of every
+        // n-gram it is made of, since there's no guarantee that the extra
+        // vocabulary is present in the corpus
+        for gram in vocabulary {
+            let gram = gram.iter().map(|word| intern(word)).collect_vec();
+
+            self.counter.add_unseen(&gram);
+        }
+
+        // <UNK> is always part of a closed vocabulary, even when nothing was cut off
+        if self.known_words.is_some() {
+            self.counter.add_unseen(&[UNKNOWN_ID]);
+        }
+
+        // once the counts were spilled, the merged counts are written to sorted tables on disk,
+        // the merge gives the n-grams of every order together, each order in sorted order
+        let (ngrams, storage) = match (self.spills.is_empty(), self.options.memory_limit) {
+            (false, Some(memory_limit)) => {
+                let storage = Storage::Disk { memory_limit };
+                let mut tables = (1..=self.order)
+                    .map(|n| TableBuilder::new(n, storage))
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                for count in self.spills.merge(self.counter) {
+                    let (gram, count) = count?;
+
+                    tables[gram.len() - 1].push(gram, count)?;
+                }
+
+                let tables = tables
+                    .into_iter()
+                    .map(|table| table.finish())
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                (tables, storage)
+            }
+            _ => (
+                self.counter
+                    .into_counts()
+                    .into_iter()
+                    .map(NgramTable::Memory)
+                    .collect(),
+                Storage::Memory,
+            ),
+        };
+
+        Ok(NgramCounts::from_ngrams(
+            self.order,
+            self.ids,
+            ngrams,
+            storage,
+            self.preprocessing,
+            self.options,
+        ))
+    }
+}
+
+// The lines of a file, a batch at a time, so that the whole file is never held in memory
+fn read_batches(file: File) -> impl Iterator<Item = io::Result<Vec<String>>> {
+    let mut lines = BufReader::new(file).lines();
+
+    std::iter::from_fn(move || {
+        let mut batch = vec![];
+
+        for line in lines.by_ref() {
+            match line {
+                Ok(line) => batch.push(line),
+                // lines that aren't valid UTF-8 are skipped, any other error stops the reading
+                Err(error) if error.kind() == ErrorKind::InvalidData => continue,
+                Err(error) => return Some(Err(error)),
+            }
+
+            if batch.len() == BATCH_LINES {
+                break;
+            }
+        }
+
+        match batch.is_empty() {
+            true => None,
+            false => Some(Ok(batch)),
+        }
+    })
+}
+
+// Run the counting of every shard of the lines in a thread of its own,
+// and give back the results in the order of the lines
+fn in_shards<T, F>(lines: &[String], threads: usize, count: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&[String]) -> T + Sync,
+{
+    if threads <= 1 {
+        return vec![count(lines)];
+    }
+
+    let shard_size = lines.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        lines
+            .chunks(shard_size)
+            .map(|shard| scope.spawn(|| count(shard)))
+            .collect_vec()
+            .into_iter()
+            .map(|thread| thread.join().expect("A counting thread panicked"))
+            .collect_vec()
+    })
+}
+
+// Count the n-grams of the sentences of the lines one by one, reusing the buffer of word IDs
+fn count_lines(
+    lines: &[String],
+    sentences: &(dyn Fn(&str) -> Vec<Vec<String>> + Sync),
+    known_words: Option<&HashSet<String>>,
+    padding: bool,
+    ids: &mut Vocabulary,
+    counter: &mut NgramCounter,
+) {
+    let mut sentence = vec![];
+
+    for words in lines.iter().flat_map(|line| sentences(line)) {
+        sentence.clear();
+
+        if padding {
+            sentence.push(SENTENCE_START_ID);
+        }
+        sentence.extend(words.into_iter().map(|word| match known_words {
+            Some(known_words) if !known_words.contains(&word) => UNKNOWN_ID,
+            _ => ids.intern(&word),
+        }));
+
+        // when padding, every sentence is padded on its own, and empty ones are skipped
+        if padding {
+            if sentence.len() == 1 {
+                continue;
+            }
+            sentence.push(SENTENCE_END_ID);
+        }
+
+        counter.add_sentence(&sentence);
+    }
+}
+
+fn count_words(
+    lines: &[String],
+    sentences: &(dyn Fn(&str) -> Vec<Vec<String>> + Sync),
+    threads: usize,
+) -> HashMap<String, u32> {
+    in_shards(lines, threads, |lines| {
+        lines
+            .iter()
+            .flat_map(|line| sentences(line))
+            .flatten()
+            .fold(HashMap::<String, u32>::new(), |mut counts, word| {
+                match counts.get_mut(&word) {
+                    Some(count) => *count += 1,
+                    None => {
+                        counts.insert(word, 1);
+                    }
+                }
+
+                counts
+            })
+    })
+    .into_iter()
+    .reduce(|total, counts| merge_hashmaps_with(counts, total, |l, r| l + r))
+    .unwrap_or_default()
+}
+
+pub(crate) fn ends_with_sentence_start(gram: &[WordId]) -> bool {
+    gram.last() == Some(&SENTENCE_START_ID)
+}
+
+// The words seen often enough, or the most frequent ones, along with the extra vocabulary
+// and the sentence markers, which are never cut off
+fn closed_vocabulary(
+    word_counts: HashMap<String, u32>,
+    vocabulary: &[Vec<String>],
+    options: &Options,
+) -> HashSet<String> {
+    let min_count = options.min_count.unwrap_or(0);
+    let max_size = options.max_vocabulary_size.unwrap_or(usize::MAX);
+
+    let mut known_words: HashSet<String> = word_counts
+        .into_iter()
+        .filter(|(word, count)| {
+            *count >= min_count && word != SENTENCE_START && word != SENTENCE_END
+        })
+        // most frequent first, ties broken alphabetically so the cut off is deterministic
+        .sorted_by(|(l_word, l_count), (r_word, r_count)| {
+            r_count.cmp(l_count).then(l_word.cmp(r_word))
+        })
+        .take(max_size)
+        .map(|(word, _count)| word)
+        .collect();
+
+    known_words.extend(vocabulary.iter().flatten().cloned());
+    known_words.extend(
+        [SENTENCE_START, SENTENCE_END, UNKNOWN]
+            .iter()
+            .map(|word| word.to_string()),
+    );
+
+    known_words
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use itertools::Itertools;
+
+    use crate::*;
+
+    fn to_gram(words: &str) -> Vec<String> {
+        words.split(' ').map(|s| s.to_string()).collect_vec()
+    }
+
+    #[test]
+    fn test_counts() {
+        let corpus = ["chicago is cold", "chicago is hot"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect_vec();
+
+        let counts = NgramCounts::new(
+            &corpus,
+            &[],
+            2,
+            &Options::new().with_sentence_boundaries(true),
+        );
+
+        assert_eq!(2, counts.order());
+        assert_eq!(8, counts.count(&[]));
+        assert_eq!(2, counts.count(&to_gram("chicago is")));
+        assert_eq!(1, counts.count(&to_gram("hot </s>")));
+        assert_eq!(0, counts.count(&to_gram("is chicago")));
+        // <s> is only ever history
+        assert_eq!(5, counts.ngrams(1).count());
+    }
+
+    #[test]
+    fn test_counting_in_threads() {
+        let corpus = [
+            "chicago is cold",
+            "",
+            "chicago is hot",
+            "africa is hot",
+            "is it cold in africa",
+            "it is",
+            "cold",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect_vec();
+        let vocabulary = vec![to_gram("is warm")];
+        let options = Options::new()
+            .with_sentence_boundaries(true)
+            .with_min_count(2);
+
+        let sequential = NgramCounts::new(&corpus, &vocabulary, 3, &options);
+
+        for threads in [2, 3, 16] {
+            let options = Options::new()
+                .with_sentence_boundaries(true)
+                .with_min_count(2)
+                .with_threads(threads);
+            let parallel = NgramCounts::new(&corpus, &vocabulary, 3, &options);
+
+            assert_eq!(sequential.vocabulary, parallel.vocabulary);
+            assert_eq!(sequential.ngrams, parallel.ngrams);
+            assert_eq!(sequential.counts_of_counts, parallel.counts_of_counts);
+            assert_eq!(sequential.total_words, parallel.total_words);
+        }
+    }
+
+    #[test]
+    fn test_counting_with_memory_limit() {
+        let corpus = (0..25_000)
+            .map(|i| format!("line {} of {} lines", i % 1000, i % 7))
+            .collect_vec();
+        let options = Options::new().with_sentence_boundaries(true);
+
+        let in_memory = NgramCounts::new(&corpus, &[], 3, &options);
+        let spilled = NgramCounts::new(&corpus, &[], 3, &options.with_memory_limit(100));
+
+        assert_eq!(in_memory.vocabulary, spilled.vocabulary);
+        assert_eq!(in_memory.ngrams, spilled.ngrams);
+        assert_eq!(in_memory.counts_of_counts, spilled.counts_of_counts);
+        assert_eq!(in_memory.total_words, spilled.total_words);
+
+        // only the first n-gram of every block of the spilled tables stays in memory
+        assert!(in_memory.ngrams_in_memory() > 10_000);
+        assert!(spilled.ngrams_in_memory() < 100);
+    }
+
+    #[test]
+    fn test_counting_a_file() {
+        let corpus = ["chicago is cold", "", "chicago is hot", "africa is hot"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect_vec();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(corpus.join("\n").as_bytes()).unwrap();
+
+        let options = Options::new()
+            .with_sentence_boundaries(true)
+            .with_min_count(2)
+            .with_memory_limit(1);
+
+        let in_memory = NgramCounts::new(&corpus, &[], 2, &options);
+        let from_file = NgramCounts::from_file(file.path(), &[], 2, &options).unwrap();
+
+        assert_eq!(in_memory.vocabulary, from_file.vocabulary);
+        assert_eq!(in_memory.ngrams, from_file.ngrams);
+        assert_eq!(2, from_file.count(&to_gram("is hot")));
+    }
+
+    #[test]
+    fn test_counting_a_file_with_invalid_lines() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"the cat sat\n\xff\xfe bad\nthe dog sat")
+            .unwrap();
+
+        let counts = NgramCounts::from_file(file.path(), &[], 2, &Options::new()).unwrap();
+
+        // the line that isn't valid UTF-8 is skipped, the lines around it are still counted
+        assert_eq!(6, counts.count(&[]));
+        assert_eq!(2, counts.count(&to_gram("the")));
+        assert_eq!(1, counts.count(&to_gram("dog sat")));
+        assert_eq!(0, counts.count(&to_gram("bad")));
+    }
+
+    #[test]
+    fn test_counting_split_sentences() {
+        let corpus = vec!["Chicago is cold.Is it hot? Dr. Smith said no.".to_string()];
+        let options = Options::new()
+            .with_sentence_boundaries(true)
+            .with_sentence_splitting(true);
+
+        let counts = NgramCounts::new(&corpus, &[], 2, &options);
+
+        assert_eq!(3, counts.count(&to_gram("</s>")));
+        assert_eq!(1, counts.count(&to_gram("<s> is")));
+        assert_eq!(1, counts.count(&to_gram("dr smith")));
+        assert_eq!(0, counts.count(&to_gram("cold is")));
+        assert_eq!(
+            vec![
+                to_gram("<s> it"),
+                to_gram("it </s>"),
+                to_gram("<s> no"),
+                to_gram("no </s>")
+            ],
+            counts.sentence_ngrams("It. No!")
+        );
+    }
+
+    #[test]
+    fn test_filtering_words_before_characters() {
+        let corpus = vec!["a cat and it sat".to_string()];
+        let options = Options::new()
+            .with_characters(false)
+            .with_token_filter(filters::StopWords::builtin("english").unwrap())
+            .with_token_filter(filters::MinLengthFilter::new(2));
+
+        let counts = NgramCounts::new(&corpus, &[], 2, &options);
+
+        // the stop words are left out as whole words, the letters of the rest are all kept
+        assert_eq!(2, counts.count(&to_gram("a")));
+        assert_eq!(0, counts.count(&to_gram("n")));
+        assert_eq!(1, counts.count(&["t".to_string(), " ".to_string()]));
+        assert_eq!(7, counts.count(&[]));
+    }
+
+    #[test]
+    fn test_models_share_counts() {
+        let corpus = ["chicago is cold", "chicago is hot"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect_vec();
+        let counts = NgramCounts::new(&corpus, &[], 2, &Options::new());
+
+        let plain = NgramModel::from_counts(&counts, Options::new());
+        let laplace = NgramModel::from_counts(&counts, Options::new().with_add_k_smoothing(1.0));
+
+        assert_eq!(0.5, plain.prob(&to_gram("is cold")));
+        assert_eq!(2.0 / 6.0, laplace.prob(&to_gram("is cold")));
+    }
+}
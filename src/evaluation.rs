@@ -0,0 +1,182 @@
+use crate::NgramModel;
+
+/// How well a model predicts some text, measured in natural logarithms.
+///
+/// Words outside of an open vocabulary and words with a probability of 0 can't be
+/// scored, so like SRILM they are counted separately and left out of the totals.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct Score {
+    pub log_probability: f64,
+    // the number of scored words, including </s> when sentence boundaries are on
+    pub words: usize,
+    pub oovs: usize,
+    pub zero_probabilities: usize,
+}
+
+impl Score {
+    /// The average number of bits needed to encode a word,
+    /// which is undefined when no word could be scored.
+    pub fn cross_entropy(&self) -> Option<f64> {
+        self.average_log_probability()
+            .map(|average| -average / std::f64::consts::LN_2)
+    }
+
+    /// Undefined when no word could be scored, like for an empty sentence.
+    pub fn perplexity(&self) -> Option<f64> {
+        self.average_log_probability()
+            .map(|average| (-average).exp())
+    }
+
+    fn average_log_probability(&self) -> Option<f64> {
+        match self.words {
+            0 => None,
+            words => Some(self.log_probability / words as f64),
+        }
+    }
+
+    fn add(&mut self, other: &Score) {
+        self.log_probability += other.log_probability;
+        self.words += other.words;
+        self.oovs += other.oovs;
+        self.zero_probabilities += other.zero_probabilities;
+    }
+}
+
+pub struct Evaluation {
+    pub sentences: Vec<Score>,
+    pub corpus: Score,
+}
+
+/// Score every line of a held-out corpus as a sentence, along with the corpus as a whole.
+pub fn evaluate(model: &NgramModel, heldout: &[String]) -> Evaluation {
+    let sentences = heldout
+        .iter()
+        .map(|sentence| score_sentence(model, sentence))
+        .collect::<Vec<_>>();
+
+    let corpus = sentences
+        .iter()
+        .fold(Score::default(), |mut corpus, score| {
+            corpus.add(score);
+
+            corpus
+        });
+
+    Evaluation { sentences, corpus }
+}
+
+pub fn score_sentence(model: &NgramModel, sentence: &str) -> Score {
+    model
+        .sentence_ngrams(sentence)
+        .iter()
+        .fold(Score::default(), |mut score, gram| {
+            let word = gram.last().expect("Scored n-grams are never empty");
+            let log_probability = model.log_prob(gram);
+
+            if !model.knows(word) {
+                score.oovs += 1;
+            } else if log_probability == f64::NEG_INFINITY {
+                score.zero_probabilities += 1;
+            } else {
+                score.log_probability += log_probability;
+                score.words += 1;
+            }
+
+            score
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::evaluation::*;
+    use crate::Options;
+
+    fn get_test_corpus() -> Vec<String> {
+        ["a b", "b", "a a b", "c b a", "a b", "b c"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_score_sentence() {
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            2,
+            Options::new().with_sentence_boundaries(true),
+        );
+
+        // P(a | <s>) * P(b | a) * P(</s> | b)
+        let expected = (3.0_f64 / 6.0 * 3.0 / 5.0 * 4.0 / 6.0).ln();
+        let score = score_sentence(&model, "a b");
+
+        assert_eq!(3, score.words);
+        assert!((expected - score.log_probability).abs() < 1e-6);
+        let perplexity = score.perplexity().unwrap();
+        assert!((perplexity - (-expected / 3.0).exp()).abs() < 1e-6);
+        assert!((score.cross_entropy().unwrap() - perplexity.log2()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_score_empty_sentence() {
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            2,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_modified_kneser_ney(),
+        );
+
+        // empty sentences aren't counted, so they aren't scored either
+        let score = score_sentence(&model, "");
+
+        assert_eq!(Score::default(), score);
+        assert_eq!(None, score.perplexity());
+        assert_eq!(None, score.cross_entropy());
+
+        let with_empty = evaluate(&model, &["a b".to_string(), "".to_string()]);
+        let without_empty = evaluate(&model, &["a b".to_string()]);
+
+        assert_eq!(without_empty.corpus, with_empty.corpus);
+    }
+
+    #[test]
+    fn test_evaluate_with_unknown_words() {
+        let heldout = vec!["a b".to_string(), "a d".to_string(), "c a".to_string()];
+
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            2,
+            Options::new().with_sentence_boundaries(true),
+        );
+        let evaluation = evaluate(&model, &heldout);
+
+        // "d" was never seen, so neither was anything after it, nor was "a" after "c"
+        assert_eq!(3, evaluation.sentences.len());
+        assert_eq!(1, evaluation.corpus.oovs);
+        assert_eq!(2, evaluation.corpus.zero_probabilities);
+        assert_eq!(3 + 1 + 2, evaluation.corpus.words);
+
+        // with a closed vocabulary "d" is scored as <UNK>
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            2,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_min_count(1)
+                .with_kneser_ney(0.75),
+        );
+        let evaluation = evaluate(&model, &heldout);
+
+        assert_eq!(0, evaluation.corpus.oovs);
+        assert_eq!(0, evaluation.corpus.zero_probabilities);
+        assert_eq!(9, evaluation.corpus.words);
+        assert!(evaluation.corpus.perplexity().unwrap().is_finite());
+    }
+}
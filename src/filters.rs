@@ -0,0 +1,383 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::utils::NUMBER;
+
+/// Decides which tokens are counted, after lines were split into words and normalized.
+///
+/// Filters are composed by keeping only the tokens that every one of them keeps.
+/// Sentence boundaries are added afterwards, so they are never filtered out.
+pub trait TokenFilter: Send + Sync {
+    fn keep(&self, token: &str) -> bool;
+}
+
+/// Drops common words that carry little meaning of their own, like `the` or `of`,
+/// regardless of their case.
+#[derive(Clone, Debug, Default)]
+pub struct StopWords {
+    words: HashSet<String>,
+}
+
+impl StopWords {
+    pub fn new<S: AsRef<str>>(words: &[S]) -> StopWords {
+        StopWords::default().with_words(words)
+    }
+
+    /// One of the built-in lists: `english`, `german`, `french` or `spanish`.
+    pub fn builtin(language: &str) -> Option<StopWords> {
+        let words = match language {
+            "english" => ENGLISH,
+            "german" => GERMAN,
+            "french" => FRENCH,
+            "spanish" => SPANISH,
+            _ => return None,
+        };
+
+        Some(StopWords::new(words))
+    }
+
+    /// A list with a word on every line, where empty lines and lines starting with `#` are skipped.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<StopWords> {
+        let words = fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        Ok(StopWords::new(&words))
+    }
+
+    /// Add words to the list, like the words of another list.
+    pub fn with_words<S: AsRef<str>>(mut self, words: &[S]) -> Self {
+        self.words
+            .extend(words.iter().map(|word| word.as_ref().to_lowercase()));
+
+        self
+    }
+}
+
+impl TokenFilter for StopWords {
+    fn keep(&self, token: &str) -> bool {
+        !self.words.contains(&token.to_lowercase())
+    }
+}
+
+/// Drops tokens without a single letter or digit, like `,` or `--`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PunctuationFilter;
+
+impl TokenFilter for PunctuationFilter {
+    fn keep(&self, token: &str) -> bool {
+        token.chars().any(char::is_alphanumeric)
+    }
+}
+
+/// Drops numbers like `42`, `3.5` or `1,000`, along with the `<NUM>` mask.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumberFilter;
+
+impl TokenFilter for NumberFilter {
+    fn keep(&self, token: &str) -> bool {
+        lazy_static! {
+            static ref NUMBER_RE: Regex = Regex::new(r"^[-+]?\d+(?:[.,:/]\d+)*%?$").unwrap();
+        }
+
+        token != NUMBER && !NUMBER_RE.is_match(token)
+    }
+}
+
+/// Drops tokens shorter than a number of characters.
+#[derive(Clone, Copy, Debug)]
+pub struct MinLengthFilter {
+    length: usize,
+}
+
+impl MinLengthFilter {
+    pub fn new(length: usize) -> MinLengthFilter {
+        MinLengthFilter { length }
+    }
+}
+
+impl TokenFilter for MinLengthFilter {
+    fn keep(&self, token: &str) -> bool {
+        token.chars().count() >= self.length
+    }
+}
+
+const ENGLISH: &[&str] = &[
+    "a",
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "all",
+    "am",
+    "an",
+    "and",
+    "any",
+    "are",
+    "aren't",
+    "as",
+    "at",
+    "be",
+    "because",
+    "been",
+    "before",
+    "being",
+    "below",
+    "between",
+    "both",
+    "but",
+    "by",
+    "can",
+    "can't",
+    "cannot",
+    "could",
+    "couldn't",
+    "did",
+    "didn't",
+    "do",
+    "does",
+    "doesn't",
+    "doing",
+    "don't",
+    "down",
+    "during",
+    "each",
+    "few",
+    "for",
+    "from",
+    "further",
+    "had",
+    "hadn't",
+    "has",
+    "hasn't",
+    "have",
+    "haven't",
+    "having",
+    "he",
+    "he'd",
+    "he'll",
+    "he's",
+    "her",
+    "here",
+    "here's",
+    "hers",
+    "herself",
+    "him",
+    "himself",
+    "his",
+    "how",
+    "how's",
+    "i",
+    "i'd",
+    "i'll",
+    "i'm",
+    "i've",
+    "if",
+    "in",
+    "into",
+    "is",
+    "isn't",
+    "it",
+    "it's",
+    "its",
+    "itself",
+    "let's",
+    "me",
+    "more",
+    "most",
+    "mustn't",
+    "my",
+    "myself",
+    "no",
+    "nor",
+    "not",
+    "of",
+    "off",
+    "on",
+    "once",
+    "only",
+    "or",
+    "other",
+    "ought",
+    "our",
+    "ours",
+    "ourselves",
+    "out",
+    "over",
+    "own",
+    "same",
+    "shan't",
+    "she",
+    "she'd",
+    "she'll",
+    "she's",
+    "should",
+    "shouldn't",
+    "so",
+    "some",
+    "such",
+    "than",
+    "that",
+    "that's",
+    "the",
+    "their",
+    "theirs",
+    "them",
+    "themselves",
+    "then",
+    "there",
+    "there's",
+    "these",
+    "they",
+    "they'd",
+    "they'll",
+    "they're",
+    "they've",
+    "this",
+    "those",
+    "through",
+    "to",
+    "too",
+    "under",
+    "until",
+    "up",
+    "very",
+    "was",
+    "wasn't",
+    "we",
+    "we'd",
+    "we'll",
+    "we're",
+    "we've",
+    "were",
+    "weren't",
+    "what",
+    "what's",
+    "when",
+    "when's",
+    "where",
+    "where's",
+    "which",
+    "while",
+    "who",
+    "who's",
+    "whom",
+    "why",
+    "why's",
+    "will",
+    "with",
+    "won't",
+    "would",
+    "wouldn't",
+    "you",
+    "you'd",
+    "you'll",
+    "you're",
+    "you've",
+    "your",
+    "yours",
+    "yourself",
+    "yourselves",
+];
+
+const GERMAN: &[&str] = &[
+    "aber", "alle", "allem", "allen", "aller", "alles", "als", "also", "am", "an", "ander",
+    "andere", "anderen", "anderer", "anderes", "auch", "auf", "aus", "bei", "bin", "bis", "bist",
+    "da", "damit", "dann", "das", "dass", "dein", "deine", "dem", "den", "denn", "der", "des",
+    "dich", "die", "dies", "diese", "diesem", "diesen", "dieser", "dieses", "dir", "doch", "dort",
+    "du", "durch", "ein", "eine", "einem", "einen", "einer", "eines", "er", "es", "euch", "euer",
+    "für", "hab", "habe", "haben", "hat", "hatte", "hier", "hin", "ich", "ihm", "ihn", "ihnen",
+    "ihr", "ihre", "im", "in", "ist", "ja", "jede", "jedem", "jeden", "jeder", "jedes", "kann",
+    "kein", "keine", "man", "mein", "meine", "mich", "mir", "mit", "nach", "nicht", "nichts",
+    "noch", "nun", "nur", "ob", "oder", "ohne", "sehr", "sein", "seine", "sich", "sie", "sind",
+    "so", "über", "um", "und", "uns", "unser", "unter", "viel", "vom", "von", "vor", "war",
+    "waren", "warst", "was", "weil", "wenn", "wer", "werde", "werden", "wie", "wir", "wird", "wo",
+    "zu", "zum", "zur",
+];
+
+const FRENCH: &[&str] = &[
+    "à", "au", "aux", "avec", "ce", "ces", "cette", "dans", "de", "des", "du", "elle", "elles",
+    "en", "est", "et", "été", "être", "eu", "il", "ils", "je", "la", "le", "les", "leur", "leurs",
+    "lui", "ma", "mais", "me", "même", "mes", "moi", "mon", "ne", "nos", "notre", "nous", "on",
+    "ont", "ou", "où", "par", "pas", "pour", "qu", "que", "qui", "sa", "se", "ses", "si", "son",
+    "sont", "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos", "votre", "vous", "y",
+];
+
+const SPANISH: &[&str] = &[
+    "a", "al", "algo", "ante", "con", "contra", "cual", "cuando", "de", "del", "desde", "donde",
+    "el", "él", "ella", "ellas", "ellos", "en", "entre", "era", "es", "esa", "ese", "eso", "esta",
+    "está", "este", "esto", "fue", "ha", "hay", "la", "las", "le", "les", "lo", "los", "me", "mi",
+    "mí", "muy", "más", "nada", "ni", "no", "nos", "o", "os", "otra", "otro", "para", "pero",
+    "por", "porque", "que", "qué", "se", "sea", "ser", "si", "sí", "sin", "sobre", "su", "sus",
+    "también", "te", "tu", "tú", "un", "una", "uno", "unos", "y", "ya", "yo",
+];
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_stop_words() {
+        let stop_words = StopWords::builtin("english").unwrap();
+
+        assert!(!stop_words.keep("the"));
+        assert!(!stop_words.keep("The"));
+        assert!(stop_words.keep("lion"));
+        assert!(StopWords::builtin("klingon").is_none());
+
+        let stop_words = stop_words.with_words(&["Lion"]);
+        assert!(!stop_words.keep("lion"));
+    }
+
+    #[test]
+    fn test_stop_words_from_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"# animals\nlion\n\n  Tiger \n").unwrap();
+
+        let stop_words = StopWords::from_file(file.path()).unwrap();
+
+        assert!(!stop_words.keep("lion"));
+        assert!(!stop_words.keep("tiger"));
+        assert!(stop_words.keep("# animals"));
+        assert!(stop_words.keep("the"));
+    }
+
+    #[test]
+    fn test_punctuation_filter() {
+        assert!(!PunctuationFilter.keep(","));
+        assert!(!PunctuationFilter.keep("--"));
+        assert!(PunctuationFilter.keep("isn't"));
+        assert!(PunctuationFilter.keep("3"));
+    }
+
+    #[test]
+    fn test_number_filter() {
+        for number in ["42", "3.5", "1,000", "-7", "50%", "12:30", NUMBER] {
+            assert!(!NumberFilter.keep(number), "{} is a number", number);
+        }
+        for word in ["3rd", "a1", "lion", "."] {
+            assert!(NumberFilter.keep(word), "{} is not a number", word);
+        }
+    }
+
+    #[test]
+    fn test_min_length_filter() {
+        let filter = MinLengthFilter::new(3);
+
+        assert!(!filter.keep("is"));
+        assert!(filter.keep("the"));
+        assert!(filter.keep("née"));
+        assert!(!filter.keep("né"));
+    }
+}
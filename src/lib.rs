@@ -4,14 +4,75 @@ extern crate test;
 
 use itertools::Itertools;
 use std::collections::HashMap;
-use utils::{count_nested, lines_to_word_lists, merge_hashmaps_with, to_hashmap_keys};
-
+use std::sync::Arc;
+
+use filters::TokenFilter;
+use normalization::{Normalizer, UnicodeForm};
+use sentences::SentenceSplitter;
+use tokenizer::{RegexTokenizer, Tokenizer};
+
+pub use counts::NgramCounts;
+pub use model::NgramModel;
+pub use vocabulary::Vocabulary;
+
+pub mod counter;
+pub mod counts;
+pub mod evaluation;
+pub mod filters;
+pub mod model;
+pub mod normalization;
+pub mod sentences;
+pub mod spill;
+mod table;
+pub mod tokenizer;
 pub mod utils;
+pub mod vocabulary;
+
+#[derive(Default, Clone)]
+enum Smoothing {
+    #[default]
+    None,
+    KneserNey {
+        discount: f64,
+    },
+    ModifiedKneserNey,
+    Katz,
+    WittenBell,
+    JelinekMercer {
+        weights: Vec<f64>,
+    },
+    StupidBackoff {
+        factor: f64,
+    },
+    AbsoluteDiscounting {
+        discount: f64,
+    },
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum GoodTuring {
+    #[default]
+    Off,
+    Basic,
+    Simple,
+}
 
 #[derive(Default)]
 pub struct Options {
-    add_k_smoothing: u32,
-    good_turing: bool,
+    add_k_smoothing: f64,
+    good_turing: GoodTuring,
+    sentence_boundaries: bool,
+    min_count: Option<u32>,
+    max_vocabulary_size: Option<usize>,
+    smoothing: Smoothing,
+    threads: usize,
+    memory_limit: Option<usize>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
+    // whether words are split into characters, with or without word boundaries
+    characters: Option<bool>,
+    normalizer: Normalizer,
+    sentence_splitter: Option<SentenceSplitter>,
+    filters: Vec<Arc<dyn TokenFilter>>,
 }
 
 impl Options {
@@ -19,168 +80,307 @@ impl Options {
         Default::default()
     }
 
-    pub fn with_add_k_smoothing(mut self, k: u32) -> Self {
+    /// Add k to every count, where k can be a fraction (Lidstone smoothing).
+    pub fn with_add_k_smoothing(mut self, k: f64) -> Self {
         self.add_k_smoothing = k;
 
         self
     }
 
     pub fn with_good_turing(mut self, on: bool) -> Self {
-        self.good_turing = on;
+        self.good_turing = match on {
+            true => GoodTuring::Basic,
+            false => GoodTuring::Off,
+        };
 
         self
     }
-}
 
-// TODO: add optional debug param
-pub fn unigrams(
-    corpus: &[String],     // lines
-    vocabulary: &[String], // optional extra vocabulary to compute n-gram probabilities for
-    options: Options,
-) -> HashMap<String, f32> {
-    let smoothing = options.add_k_smoothing;
+    /// Use Gale & Sampson's Simple Good-Turing counts, which smooth the counts of counts
+    /// so that high counts don't drop to 0 when nothing was seen one more time.
+    /// Longer n-grams spread what their history has left over the words never seen after it.
+    pub fn with_simple_good_turing(mut self, on: bool) -> Self {
+        self.good_turing = match on {
+            true => GoodTuring::Simple,
+            false => GoodTuring::Off,
+        };
 
-    let word_lists = lines_to_word_lists(corpus);
+        self
+    }
 
-    // create initial 0 counts of extra vocabulary
-    // since there's no guarantee that the extra vocabulary
-    // is present in the corpus
-    let initial_word_counts: HashMap<String, u32> = to_hashmap_keys(vocabulary, |_| 0);
+    /// Pad every line with `<s>` and `</s>`, so that the first and last words
+    /// of a sentence get probabilities as well.
+    pub fn with_sentence_boundaries(mut self, on: bool) -> Self {
+        self.sentence_boundaries = on;
 
-    // add the actual counts from corpus
-    let word_counts = count_nested(&word_lists);
-    // TODO: only calculate this when good turing is on
+        self
+    }
 
-    let total_counts = merge_hashmaps_with(word_counts, initial_word_counts, |l, r| l + r);
+    /// Close the vocabulary to words seen at least `count` times,
+    /// every other word is counted and looked up as `<UNK>`.
+    pub fn with_min_count(mut self, count: u32) -> Self {
+        self.min_count = Some(count);
 
-    let counts_for_words =
-        total_counts
-            .iter()
-            .fold(HashMap::<u32, u32>::new(), |mut counts, (_word, count)| {
-                *counts.entry(*count).or_default() += 1;
+        self
+    }
 
-                counts
-            });
-    let total_words: u32 = total_counts.values().sum::<u32>();
-    let vocabulary_size = total_counts.keys().count() as u32;
+    /// Close the vocabulary to the `size` most frequent words,
+    /// every other word is counted and looked up as `<UNK>`.
+    pub fn with_max_vocabulary_size(mut self, size: usize) -> Self {
+        self.max_vocabulary_size = Some(size);
 
-    let probabilities = total_counts
-        .iter()
-        .map(|(word, &count)| {
-            let gram = word.clone();
-
-            // smooth count if good turing is enabled
-            let c: f32 = match options.good_turing {
-                false => count as f32,
-                true => {
-                    let c_1 = count + 1;
-                    let n_1: f32 = *counts_for_words.get(&count).unwrap_or(&0) as f32;
-                    let n_2 = *counts_for_words.get(&(c_1)).unwrap_or(&0) as f32;
-
-                    match count {
-                        0 => n_2,
-                        _ => c_1 as f32 * (n_2 / n_1),
-                    }
-                }
-            };
-
-            let unrounded_probability =
-                (c + smoothing as f32) / (total_words + (smoothing * vocabulary_size)) as f32;
-            // During testing, the probabilities were much smaller than 0.00
-            // so I'm going to disable it for now
-            // let rounded_probability = (unrounded_probability * 100.0).round() / 100.0;
-
-            (gram, unrounded_probability)
-        })
-        .collect();
+        self
+    }
 
-    probabilities
-}
+    /// Use interpolated Kneser-Ney smoothing with a fixed discount between 0 and 1,
+    /// instead of add-k and Good-Turing.
+    pub fn with_kneser_ney(mut self, discount: f64) -> Self {
+        assert!(
+            discount > 0.0 && discount <= 1.0,
+            "The Kneser-Ney discount must be between 0 and 1"
+        );
+        self.smoothing = Smoothing::KneserNey { discount };
 
-pub fn bigrams(
-    corpus: &[String],               // lines
-    vocabulary: &[(String, String)], // optional extra vocabulary to compute n-gram probabilities for
-    options: Options,
-) -> HashMap<(String, String), f32> {
-    let smoothing = options.add_k_smoothing;
+        self
+    }
+
+    /// Use interpolated modified Kneser-Ney smoothing, with separate discounts for n-grams
+    /// seen once, twice, and three or more times, estimated from the counts of counts.
+    pub fn with_modified_kneser_ney(mut self) -> Self {
+        self.smoothing = Smoothing::ModifiedKneserNey;
+
+        self
+    }
+
+    /// Use Katz backoff, which discounts seen n-grams with Good-Turing and backs off
+    /// to lower orders for unseen ones. The unigrams still use add-k and Good-Turing.
+    pub fn with_katz_backoff(mut self) -> Self {
+        self.smoothing = Smoothing::Katz;
+
+        self
+    }
+
+    /// Use interpolated Witten-Bell smoothing, which weighs the lower orders
+    /// by the number of distinct words seen after a history.
+    pub fn with_witten_bell(mut self) -> Self {
+        self.smoothing = Smoothing::WittenBell;
+
+        self
+    }
+
+    /// Use Jelinek-Mercer interpolation of the unigram, bigram and higher estimates,
+    /// with one weight for every order starting at unigrams. Without weights every
+    /// order weighs the same, until they are learned on held-out data.
+    pub fn with_jelinek_mercer(mut self, weights: &[f64]) -> Self {
+        self.smoothing = Smoothing::JelinekMercer {
+            weights: weights.to_vec(),
+        };
+
+        self
+    }
+
+    /// Score n-grams with Stupid Backoff, which backs off to lower orders with a fixed factor,
+    /// 0.4 in the original paper. It needs nothing but the counts, but its scores are not
+    /// normalized probabilities.
+    pub fn with_stupid_backoff(mut self, factor: f64) -> Self {
+        self.smoothing = Smoothing::StupidBackoff { factor };
+
+        self
+    }
+
+    /// Use interpolated absolute discounting, which subtracts a fixed discount between 0 and 1
+    /// from every seen n-gram and gives the freed mass to the unigrams.
+    pub fn with_absolute_discounting(mut self, discount: f64) -> Self {
+        assert!(
+            discount > 0.0 && discount <= 1.0,
+            "The absolute discount must be between 0 and 1"
+        );
+        self.smoothing = Smoothing::AbsoluteDiscounting { discount };
+
+        self
+    }
+
+    /// Count the corpus in this many threads, each counting a shard of the lines.
+    /// The counts are the same as when counting in one thread, which is the default.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+
+        self
+    }
+
+    /// Keep at most about this many distinct n-grams in memory while counting, and spill
+    /// sorted partial counts to temporary files beyond that, which are merged at the end.
+    ///
+    /// Once spilled, the merged counts, and the tables models derive from them, stay in
+    /// sorted temporary files, and are looked up a block at a time. Only the vocabulary,
+    /// the counts of counts, and the first n-gram of every block are kept in memory.
+    pub fn with_memory_limit(mut self, ngrams: usize) -> Self {
+        self.memory_limit = Some(ngrams);
+
+        self
+    }
+
+    /// Split lines into words with this tokenizer instead of the `[\w']+` regex.
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Some(Arc::new(tokenizer));
+
+        self
+    }
+
+    /// Count the characters of words instead of the words themselves, optionally with
+    /// every word wrapped in `<w>` and `</w>`, for character n-gram models.
+    ///
+    /// The words are split off by the tokenizer, normalized and filtered as whole words,
+    /// and only then split into characters.
+    pub fn with_characters(mut self, word_boundaries: bool) -> Self {
+        self.characters = Some(word_boundaries);
+
+        self
+    }
+
+    /// Keep the case of words, instead of lowercasing them.
+    pub fn with_preserved_case(mut self, on: bool) -> Self {
+        self.normalizer.preserve_case = on;
+
+        self
+    }
+
+    /// Bring text into Unicode normalization form C, which composes characters
+    /// with their accents, so that the same word is always spelled the same way.
+    /// Turning it off leaves normalization form KC on.
+    pub fn with_nfc_normalization(mut self, on: bool) -> Self {
+        self.normalizer.unicode_form = match (on, self.normalizer.unicode_form) {
+            (true, _) => UnicodeForm::Nfc,
+            (false, UnicodeForm::Nfc) => UnicodeForm::None,
+            (false, form) => form,
+        };
+
+        self
+    }
+
+    /// Bring text into Unicode normalization form KC, which also replaces
+    /// compatibility characters like ligatures and full-width letters.
+    /// Turning it off leaves normalization form C on.
+    pub fn with_nfkc_normalization(mut self, on: bool) -> Self {
+        self.normalizer.unicode_form = match (on, self.normalizer.unicode_form) {
+            (true, _) => UnicodeForm::Nfkc,
+            (false, UnicodeForm::Nfkc) => UnicodeForm::None,
+            (false, form) => form,
+        };
+
+        self
+    }
+
+    /// Replace curly quotes and apostrophes with straight ones, so that `didn’t` is `didn't`.
+    pub fn with_quote_folding(mut self, on: bool) -> Self {
+        self.normalizer.fold_quotes = on;
+
+        self
+    }
+
+    /// Remove accents from letters, so that `café` is `cafe`.
+    pub fn with_accent_stripping(mut self, on: bool) -> Self {
+        self.normalizer.strip_accents = on;
+
+        self
+    }
+
+    /// Count every number as `<NUM>`.
+    pub fn with_number_masking(mut self, on: bool) -> Self {
+        self.normalizer.mask_numbers = on;
+
+        self
+    }
+
+    /// Count every URL as `<URL>`.
+    pub fn with_url_masking(mut self, on: bool) -> Self {
+        self.normalizer.mask_urls = on;
+
+        self
+    }
+
+    /// Count every email address as `<EMAIL>`.
+    pub fn with_email_masking(mut self, on: bool) -> Self {
+        self.normalizer.mask_emails = on;
+
+        self
+    }
+
+    /// Split lines into sentences before counting, so that a line can hold several sentences,
+    /// and n-grams never cross from one sentence into the next.
+    pub fn with_sentence_splitting(mut self, on: bool) -> Self {
+        self.sentence_splitter = on.then(SentenceSplitter::default);
+
+        self
+    }
 
-    let word_lists: Vec<Vec<String>> = lines_to_word_lists(corpus);
+    /// Split lines into sentences with this splitter, which can know more abbreviations.
+    pub fn with_sentence_splitter(mut self, splitter: SentenceSplitter) -> Self {
+        self.sentence_splitter = Some(splitter);
 
-    let initial_biword_counts = to_hashmap_keys(vocabulary, |_| 0);
+        self
+    }
 
-    let word_counts = count_nested(&word_lists);
+    /// Only count the tokens this filter keeps, along with every filter added before.
+    pub fn with_token_filter(mut self, filter: impl TokenFilter + 'static) -> Self {
+        self.filters.push(Arc::new(filter));
 
-    let (mut vocabulary_as_list, right): (Vec<_>, Vec<_>) = vocabulary.iter().cloned().unzip();
+        self
+    }
 
-    vocabulary_as_list.extend(right);
+    fn tokenizer(&self) -> Arc<dyn Tokenizer> {
+        match &self.tokenizer {
+            Some(tokenizer) => tokenizer.clone(),
+            None => Arc::new(RegexTokenizer::default()),
+        }
+    }
 
-    let deduped_voc = vocabulary_as_list
+    fn closed_vocabulary(&self) -> bool {
+        self.min_count.is_some() || self.max_vocabulary_size.is_some()
+    }
+}
+
+// TODO: add optional debug param
+pub fn unigrams(
+    corpus: &[String],     // lines
+    vocabulary: &[String], // optional extra vocabulary to compute n-gram probabilities for
+    options: Options,
+) -> HashMap<String, f64> {
+    let vocabulary = vocabulary
         .iter()
-        .filter(|word| !word_counts.contains_key(&word.to_string()))
+        .map(|word| vec![word.clone()])
         .collect_vec();
 
-    let vocabulary_size = word_counts.keys().count() as u32 + deduped_voc.len() as u32;
+    let model = NgramModel::train(corpus, &vocabulary, 1, options);
 
-    let biword_lists: Vec<Vec<(String, String)>> = word_lists
+    model
+        .probabilities(1)
+        .into_iter()
+        .map(|(mut gram, probability)| (gram.remove(0), probability))
+        .collect()
+}
+
+pub fn bigrams(
+    corpus: &[String],               // lines
+    vocabulary: &[(String, String)], // optional extra vocabulary to compute n-gram probabilities for
+    options: Options,
+) -> HashMap<(String, String), f64> {
+    let vocabulary = vocabulary
         .iter()
-        .map(|words| {
-            words
-                .iter()
-                .tuple_windows()
-                .map(|(first_word, second_word)| (first_word.to_string(), second_word.to_string()))
-                .collect_vec()
-        })
+        .map(|(first_word, second_word)| vec![first_word.clone(), second_word.clone()])
         .collect_vec();
 
-    let biword_counts = count_nested(&biword_lists);
+    let model = NgramModel::train(corpus, &vocabulary, 2, options);
 
-    let total_counts = merge_hashmaps_with(biword_counts, initial_biword_counts, |l, r| l + r);
-    let counts_for_bigrams =
-        total_counts
-            .iter()
-            .fold(HashMap::<u32, u32>::new(), |mut counts, (_words, count)| {
-                *counts.entry(*count).or_default() += 1;
-
-                counts
-            });
+    model
+        .probabilities(2)
+        .into_iter()
+        .map(|(gram, probability)| {
+            let (first_word, second_word) = gram.into_iter().collect_tuple().unwrap();
 
-    // TODO: this would be much faster if we stored the counts in a struct
-    let probabilities = total_counts
-        .iter()
-        .map(|(words, &count)| {
-            let gram = words.clone();
-            let probability: f32 = {
-                let first_word = words.0.clone();
-                let first_word_count = *word_counts
-                    .get(&first_word)
-                    .expect("Should not reach this state");
-
-                // smooth count if good turing is enabled
-                let c: f32 = match options.good_turing {
-                    false => count as f32,
-                    true => {
-                        let c_1 = count + 1;
-                        let n_1: f32 = *counts_for_bigrams.get(&count).unwrap_or(&0) as f32;
-                        let n_2 = *counts_for_bigrams.get(&(c_1)).unwrap_or(&0) as f32;
-
-                        match count {
-                            0 => n_2,
-                            _ => c_1 as f32 * (n_2 / n_1),
-                        }
-                    }
-                };
-
-                let unrounded_probability = (c + smoothing as f32) as f32
-                    / (first_word_count + (vocabulary_size * smoothing)) as f32;
-
-                unrounded_probability
-            };
-
-            (gram, probability)
+            ((first_word, second_word), probability)
         })
-        .collect();
-
-    probabilities
+        .collect()
 }
 
 #[cfg(test)]
@@ -195,6 +395,17 @@ mod tests {
         list.iter().map(|s| s.to_string()).collect_vec()
     }
 
+    // the expected probabilities are rounded to two decimal places
+    fn round_probabilities<K>(probabilities: HashMap<K, f64>) -> HashMap<K, f64>
+    where
+        K: std::hash::Hash + Eq,
+    {
+        probabilities
+            .into_iter()
+            .map(|(gram, p)| (gram, (p * 100.0).round() / 100.0))
+            .collect()
+    }
+
     fn get_test_corpus_1() -> Vec<String> {
         let corpus: Vec<&str> = vec![
             "chicago is",
@@ -245,7 +456,7 @@ mod tests {
 
         let vocabulary = vec!["hot".to_string()];
 
-        let mut expected_unigrams: HashMap<String, f32> = HashMap::new();
+        let mut expected_unigrams: HashMap<String, f64> = HashMap::new();
         expected_unigrams.insert("chicago".to_string(), 0.22);
         expected_unigrams.insert("is".to_string(), 0.44);
         expected_unigrams.insert("cold".to_string(), 0.33);
@@ -253,7 +464,7 @@ mod tests {
 
         let actual_unigrams = crate::unigrams(&corpus, &vocabulary, Options::new());
 
-        assert_eq!(expected_unigrams, actual_unigrams);
+        assert_eq!(expected_unigrams, round_probabilities(actual_unigrams));
     }
 
     #[bench]
@@ -270,16 +481,19 @@ mod tests {
 
         let vocabulary = vec!["hot".to_string()];
 
-        let mut expected_unigrams: HashMap<String, f32> = HashMap::new();
+        let mut expected_unigrams: HashMap<String, f64> = HashMap::new();
         expected_unigrams.insert("chicago".to_string(), 0.23);
         expected_unigrams.insert("is".to_string(), 0.41);
         expected_unigrams.insert("cold".to_string(), 0.32);
         expected_unigrams.insert("hot".to_string(), 0.05);
 
-        let actual_unigrams =
-            crate::unigrams(&corpus, &vocabulary, Options::new().with_add_k_smoothing(1));
+        let actual_unigrams = crate::unigrams(
+            &corpus,
+            &vocabulary,
+            Options::new().with_add_k_smoothing(1.0),
+        );
 
-        assert_eq!(expected_unigrams, actual_unigrams);
+        assert_eq!(expected_unigrams, round_probabilities(actual_unigrams));
     }
 
     #[test]
@@ -288,26 +502,161 @@ mod tests {
 
         let vocabulary = to_vec_of_string(vec!["catfish", "bass"]);
 
-        let actual = crate::unigrams(&corpus, &vocabulary, Options::new().with_good_turing(true));
+        let actual = round_probabilities(crate::unigrams(
+            &corpus,
+            &vocabulary,
+            Options::new().with_good_turing(true),
+        ));
 
         assert_eq!(0.04, actual["trout"]);
         assert_eq!(0.17, actual["bass"]);
     }
 
+    #[test]
+    fn test_unigrams_with_simple_good_turing() {
+        let corpus: Vec<String> = get_test_corpus_2_species();
+
+        let vocabulary = to_vec_of_string(vec!["catfish", "bass"]);
+
+        let actual = round_probabilities(crate::unigrams(
+            &corpus,
+            &vocabulary,
+            Options::new().with_simple_good_turing(true),
+        ));
+
+        assert_eq!(0.04, actual["trout"]);
+        assert_eq!(0.17, actual["bass"]);
+        assert_eq!(0.49, actual["carp"]);
+    }
+
+    #[test]
+    fn test_bigrams_with_simple_good_turing() {
+        let corpus: Vec<String> = get_test_corpus_1();
+
+        let words = ["chicago", "is", "cold"];
+        let vocabulary = words
+            .iter()
+            .cartesian_product(words)
+            .map(|(first_word, second_word)| (first_word.to_string(), second_word.to_string()))
+            .collect_vec();
+
+        let actual = bigrams(
+            &corpus,
+            &vocabulary,
+            Options::new().with_simple_good_turing(true),
+        );
+
+        // the smoothed count of "chicago is" is above its count, so it keeps all of "chicago",
+        // and the one of "is cold" leaves a quarter of a count to the words never seen after "is"
+        assert_eq!(1.0, actual[&("chicago".to_string(), "is".to_string())]);
+        assert_eq!(0.0, actual[&("chicago".to_string(), "cold".to_string())]);
+        assert_eq!(0.9375, actual[&("is".to_string(), "cold".to_string())]);
+        assert_eq!(0.03125, actual[&("is".to_string(), "chicago".to_string())]);
+
+        for history in ["chicago", "is"] {
+            let total: f64 = words
+                .iter()
+                .map(|word| actual[&(history.to_string(), word.to_string())])
+                .sum();
+
+            assert!((1.0 - total).abs() < 1e-6, "{} sums to {}", history, total);
+        }
+    }
+
+    #[test]
+    fn test_unigrams_with_unicode_forms() {
+        // an e followed by a combining acute accent, and a ligature
+        let corpus = to_vec_of_string(vec!["cafe\u{301} \u{FB01}ne"]);
+
+        let nfkc = crate::unigrams(
+            &corpus,
+            &[],
+            Options::new()
+                .with_nfkc_normalization(true)
+                .with_nfc_normalization(false),
+        );
+        assert_eq!(0.5, nfkc["café"]);
+        assert_eq!(0.5, nfkc["fine"]);
+
+        let nfc = crate::unigrams(
+            &corpus,
+            &[],
+            Options::new()
+                .with_nfc_normalization(true)
+                .with_nfkc_normalization(false),
+        );
+        assert_eq!(0.5, nfc["café"]);
+        assert_eq!(0.5, nfc["\u{FB01}ne"]);
+
+        let off = crate::unigrams(
+            &corpus,
+            &[],
+            Options::new()
+                .with_nfkc_normalization(true)
+                .with_nfkc_normalization(false),
+        );
+        assert_eq!(0.5, off["cafe\u{301}"]);
+    }
+
+    #[test]
+    fn test_unigrams_with_tokenizer() {
+        let corpus = to_vec_of_string(vec!["a well-known fact", "well known"]);
+
+        let actual = crate::unigrams(
+            &corpus,
+            &[],
+            Options::new().with_tokenizer(tokenizer::WhitespaceTokenizer),
+        );
+
+        assert_eq!(0.2, actual["well-known"]);
+        assert_eq!(0.2, actual["well"]);
+        assert_eq!(5, actual.len());
+    }
+
+    #[test]
+    fn test_bigrams_with_token_filters() {
+        let corpus = to_vec_of_string(vec!["The lion is in the den.", "A lion, 3 tigers"]);
+
+        let actual = bigrams(
+            &corpus,
+            &[],
+            Options::new()
+                .with_tokenizer(tokenizer::PunctuationTokenizer)
+                .with_token_filter(filters::StopWords::builtin("english").unwrap())
+                .with_token_filter(filters::PunctuationFilter)
+                .with_token_filter(filters::NumberFilter),
+        );
+
+        assert_eq!(0.5, actual[&("lion".to_string(), "den".to_string())]);
+        assert_eq!(0.5, actual[&("lion".to_string(), "tigers".to_string())]);
+        assert_eq!(2, actual.len());
+    }
+
+    #[test]
+    fn test_character_bigrams() {
+        let corpus = to_vec_of_string(vec!["aab", "ab"]);
+
+        let actual = bigrams(&corpus, &[], Options::new().with_characters(true));
+
+        assert_eq!(1.0, actual[&("<w>".to_string(), "a".to_string())]);
+        assert_eq!(1.0 / 3.0, actual[&("a".to_string(), "a".to_string())]);
+        assert_eq!(1.0, actual[&("b".to_string(), "</w>".to_string())]);
+    }
+
     #[test]
     fn test_bigrams() {
         let corpus: Vec<String> = get_test_corpus_1();
 
         let vocabulary = vec![("is".to_string(), "hot".to_string())];
 
-        let mut expected_bigrams: HashMap<(String, String), f32> = HashMap::new();
+        let mut expected_bigrams: HashMap<(String, String), f64> = HashMap::new();
         expected_bigrams.insert(("chicago".to_string(), "is".to_string()), 0.50);
         expected_bigrams.insert(("is".to_string(), "cold".to_string()), 0.50);
         expected_bigrams.insert(("is".to_string(), "hot".to_string()), 0.00);
 
         let actual_bigrams = bigrams(&corpus, &vocabulary, Options::new());
 
-        assert_eq!(expected_bigrams, actual_bigrams);
+        assert_eq!(expected_bigrams, round_probabilities(actual_bigrams));
     }
 
     #[test]
@@ -316,13 +665,23 @@ mod tests {
 
         let vocabulary = vec![("is".to_string(), "hot".to_string())];
 
-        let mut expected_bigrams: HashMap<(String, String), f32> = HashMap::new();
+        let mut expected_bigrams: HashMap<(String, String), f64> = HashMap::new();
         expected_bigrams.insert(("chicago".to_string(), "is".to_string()), 0.38);
         expected_bigrams.insert(("is".to_string(), "cold".to_string()), 0.42);
         expected_bigrams.insert(("is".to_string(), "hot".to_string()), 0.08);
 
-        let actual_bigrams = bigrams(&corpus, &vocabulary, Options::new().with_add_k_smoothing(1));
+        let actual_bigrams = bigrams(
+            &corpus,
+            &vocabulary,
+            Options::new().with_add_k_smoothing(1.0),
+        );
 
-        assert_eq!(expected_bigrams, actual_bigrams);
+        assert_eq!(expected_bigrams, round_probabilities(actual_bigrams));
+    }
+
+    #[test]
+    #[should_panic(expected = "The Kneser-Ney discount must be between 0 and 1")]
+    fn test_kneser_ney_discount_out_of_range() {
+        Options::new().with_kneser_ney(1.5);
     }
 }
@@ -1,58 +1,302 @@
 use itertools::Itertools;
-use n_gram::{bigrams, unigrams, utils::line_to_words, Options};
+use n_gram::{
+    evaluation::evaluate,
+    filters::{MinLengthFilter, NumberFilter, PunctuationFilter, StopWords},
+    tokenizer::{PunctuationTokenizer, UnicodeWordTokenizer, WhitespaceTokenizer},
+    NgramCounts, NgramModel, Options,
+};
 use std::{
     collections::HashMap,
-    fmt::Display,
     fs::File,
-    io::{BufRead, BufReader, LineWriter, Write},
+    io::{BufRead, BufReader, ErrorKind, LineWriter, Write},
 };
 
+const USAGE: &str = "Usage:
+\tcargo run -- [path] [--order N]
+\tcargo run -- perplexity [training path] [held-out path] [--order N] [--smoothing METHOD] [--min-count N]
+
+Smoothing methods: none, laplace, kneser-ney, modified-kneser-ney (default),
+katz, witten-bell, absolute-discounting
+
+Counting options, for both:
+\t--threads N
+\t\tcount in N threads, by default one for every core
+\t--memory-limit N
+\t\tkeep about N n-grams in memory while counting, and the rest in temporary files
+\t--tokenizer TOKENIZER
+\t\tsplit lines into words with regex (default), whitespace, unicode or punctuation,
+\t\tor into letters with characters or padded-characters
+\t--normalize STEPS
+\t\tcomma separated normalization steps out of preserve-case, nfc, nfkc,
+\t\tquotes, accents, numbers, urls and emails
+\t--sentences lines|split
+\t\ttreat every line as a sentence (default), or split lines into sentences
+\t--stop-words LIST
+\t\tleave out the stop words of english, german, french or spanish, or of a file
+\t\twith a word on every line
+\t--filter FILTERS
+\t\tcomma separated filters out of punctuation, numbers and min-length=N";
+
 fn main() {
-    // convert from xml to line separated
     let args: Vec<_> = std::env::args().collect();
 
-    if args.len() != 2 {
-        println!("Usage:\n\tcargo run -- [path]");
-        std::process::exit(1);
+    let (positional, flags) = parse_args(&args[1..]);
+
+    let order = match flags.get("order") {
+        None => 2,
+        Some(order) => match order.parse::<usize>() {
+            Ok(order) if order > 0 => order,
+            _ => exit_with_usage("The order must be a positive number"),
+        },
+    };
+
+    let threads = match flags.get("threads") {
+        None => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        Some(threads) => match threads.parse::<usize>() {
+            Ok(threads) if threads > 0 => threads,
+            _ => exit_with_usage("The number of threads must be a positive number"),
+        },
+    };
+
+    let counting = Options::new().with_threads(threads);
+    let counting = match flags.get("tokenizer").copied().unwrap_or("regex") {
+        "regex" => counting,
+        "whitespace" => counting.with_tokenizer(WhitespaceTokenizer),
+        "unicode" => counting.with_tokenizer(UnicodeWordTokenizer),
+        "punctuation" => counting.with_tokenizer(PunctuationTokenizer),
+        "characters" => counting.with_characters(false),
+        "padded-characters" => counting.with_characters(true),
+        _ => exit_with_usage("Unknown tokenizer"),
+    };
+    let counting = match flags.get("sentences").copied().unwrap_or("lines") {
+        "lines" => counting,
+        "split" => counting.with_sentence_splitting(true),
+        _ => exit_with_usage("Sentences are either lines or split"),
+    };
+    let counting = match flags.get("memory-limit") {
+        None => counting,
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 => counting.with_memory_limit(limit),
+            _ => exit_with_usage("The memory limit must be a positive number of n-grams"),
+        },
+    };
+
+    let counting = flags
+        .get("normalize")
+        .map_or(vec![], |steps| steps.split(',').collect_vec())
+        .into_iter()
+        .fold(counting, |counting, step| match step {
+            "preserve-case" => counting.with_preserved_case(true),
+            "nfc" => counting.with_nfc_normalization(true),
+            "nfkc" => counting.with_nfkc_normalization(true),
+            "quotes" => counting.with_quote_folding(true),
+            "accents" => counting.with_accent_stripping(true),
+            "numbers" => counting.with_number_masking(true),
+            "urls" => counting.with_url_masking(true),
+            "emails" => counting.with_email_masking(true),
+            _ => exit_with_usage(&format!("Unknown normalization step {}", step)),
+        });
+
+    let counting = match flags.get("stop-words") {
+        None => counting,
+        Some(list) => match StopWords::builtin(list) {
+            Some(stop_words) => counting.with_token_filter(stop_words),
+            // anything that isn't a built-in list is a file with a stop word on every line
+            None => match StopWords::from_file(list) {
+                Ok(stop_words) => counting.with_token_filter(stop_words),
+                Err(_) => exit_with_usage(&format!("Failed to read the stop words in {}", list)),
+            },
+        },
+    };
+
+    let counting = flags
+        .get("filter")
+        .map_or(vec![], |filters| filters.split(',').collect_vec())
+        .into_iter()
+        .fold(counting, |counting, filter| match filter.split_once('=') {
+            None if filter == "punctuation" => counting.with_token_filter(PunctuationFilter),
+            None if filter == "numbers" => counting.with_token_filter(NumberFilter),
+            Some(("min-length", length)) => match length.parse::<usize>() {
+                Ok(length) => counting.with_token_filter(MinLengthFilter::new(length)),
+                _ => exit_with_usage("The minimum length must be a number"),
+            },
+            _ => exit_with_usage(&format!("Unknown filter {}", filter)),
+        });
+
+    match positional[..] {
+        [path] => write_probabilities(path, order, counting),
+        ["perplexity", training_path, heldout_path] => {
+            write_perplexity(training_path, heldout_path, order, counting, &flags)
+        }
+        _ => exit_with_usage(""),
     }
+}
 
-    let path = &args[1];
+fn write_probabilities(path: &str, order: usize, counting: Options) {
+    // the corpus is counted once, every variant only smooths the counts differently
+    let counts =
+        NgramCounts::from_file(path, &[], order, &counting).expect("Failed to count input file");
 
-    let file = File::open(path).expect("Failed to read input file");
-    let reader = BufReader::new(file);
+    let variants = [
+        ("", Options::new()),
+        (".laplacian", Options::new().with_add_k_smoothing(1.0)),
+        (".good_turing", Options::new().with_good_turing(true)),
+    ];
 
-    let lines = reader
-        .lines()
-        .filter_map(|l| match l {
-            Ok(line) => Some(line),
-            _ => None,
-        })
-        .collect_vec();
+    for (suffix, options) in variants {
+        let model = NgramModel::from_counts(&counts, options);
+
+        for n in 1..=order {
+            let prefix = ngrams_name(n) + suffix;
+            // written one at a time, since counts spilled to disk are never all in memory
+            let probabilities = model.iter_probabilities(n);
+
+            // keep the format of unigrams and bigrams as plain words and pairs
+            match n {
+                1 => write_ps_to_csv(
+                    path,
+                    &prefix,
+                    probabilities.map(|(mut gram, p)| (gram.remove(0), p)),
+                ),
+                2 => write_ps_to_csv(
+                    path,
+                    &prefix,
+                    probabilities
+                        .map(|(gram, p)| (gram.into_iter().collect_tuple::<(_, _)>().unwrap(), p)),
+                ),
+                _ => write_ps_to_csv(path, &prefix, probabilities),
+            }
+        }
+    }
+}
+
+fn write_perplexity(
+    training_path: &str,
+    heldout_path: &str,
+    order: usize,
+    counting: Options,
+    flags: &HashMap<&str, &str>,
+) {
+    let options = counting.with_sentence_boundaries(true);
 
-    let p_unigrams = unigrams(&lines, &Vec::new(), Options::new());
-    write_ps_to_csv::<String>(path, "unigrams", p_unigrams);
+    let options = match flags.get("min-count") {
+        None => options,
+        Some(count) => match count.parse::<u32>() {
+            Ok(count) => options.with_min_count(count),
+            _ => exit_with_usage("The minimum count must be a number"),
+        },
+    };
 
-    let p_bigrams = bigrams(&lines, &Vec::new(), Options::new());
-    write_ps_to_csv::<(String, String)>(path, "bigrams", p_bigrams);
+    let options = match flags
+        .get("smoothing")
+        .copied()
+        .unwrap_or("modified-kneser-ney")
+    {
+        "none" => options,
+        "laplace" => options.with_add_k_smoothing(1.0),
+        "kneser-ney" => options.with_kneser_ney(0.75),
+        "modified-kneser-ney" => options.with_modified_kneser_ney(),
+        "katz" => options.with_katz_backoff(),
+        "witten-bell" => options.with_witten_bell(),
+        "absolute-discounting" => options.with_absolute_discounting(0.75),
+        _ => exit_with_usage("Unknown smoothing method"),
+    };
 
-    let p_unigrams_with_laplacian =
-        unigrams(&lines, &Vec::new(), Options::new().with_add_k_smoothing(1));
-    write_ps_to_csv::<String>(path, "unigrams.laplacian", p_unigrams_with_laplacian);
+    let counts = NgramCounts::from_file(training_path, &[], order, &options)
+        .expect("Failed to count training file");
+    let model = NgramModel::from_counts(&counts, options);
 
-    let p_bigrams_with_laplacian =
-        bigrams(&lines, &Vec::new(), Options::new().with_add_k_smoothing(1));
-    write_ps_to_csv::<(String, String)>(path, "bigrams.laplacian", p_bigrams_with_laplacian);
+    let heldout = read_lines(heldout_path);
+    let evaluation = evaluate(&model, &heldout);
 
-    let p_unigrams_with_good_turing =
-        unigrams(&lines, &Vec::new(), Options::new().with_good_turing(true));
-    write_ps_to_csv::<String>(path, "unigrams.good_turing", p_unigrams_with_good_turing);
+    println!("log p\tperplexity\tsentence");
+    for (sentence, score) in heldout.iter().zip(&evaluation.sentences) {
+        println!(
+            "{:.4}\t{}\t{}",
+            score.log_probability,
+            undefined_if_none(score.perplexity()),
+            sentence
+        );
+    }
+
+    let corpus = evaluation.corpus;
+    println!();
+    println!(
+        "{} sentences, {} words, {} OOVs, {} zero probabilities",
+        heldout.len(),
+        corpus.words,
+        corpus.oovs,
+        corpus.zero_probabilities
+    );
+    println!("log p: {:.4}", corpus.log_probability);
+    println!(
+        "cross-entropy: {} bits per word",
+        undefined_if_none(corpus.cross_entropy())
+    );
+    println!("perplexity: {}", undefined_if_none(corpus.perplexity()));
+}
+
+// the perplexity and cross-entropy of text without a single scored word are undefined
+fn undefined_if_none(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.4}", value),
+        None => "undefined".to_string(),
+    }
+}
+
+// split the arguments into positional ones and the values of --flags
+fn parse_args(args: &[String]) -> (Vec<&str>, HashMap<&str, &str>) {
+    let mut positional = vec![];
+    let mut flags = HashMap::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            Some(flag) => match args.next() {
+                Some(value) => {
+                    flags.insert(flag, value.as_str());
+                }
+                None => exit_with_usage(&format!("--{} needs a value", flag)),
+            },
+            None => positional.push(arg.as_str()),
+        }
+    }
+
+    (positional, flags)
+}
 
-    let p_bigrams_with_good_turing =
-        bigrams(&lines, &Vec::new(), Options::new().with_good_turing(true));
-    write_ps_to_csv::<(String, String)>(path, "bigrams.good_turing", p_bigrams_with_good_turing);
+fn exit_with_usage(message: &str) -> ! {
+    println!("{}\n{}", message, USAGE);
+    std::process::exit(1);
+}
+
+fn read_lines(path: &str) -> Vec<String> {
+    let file = File::open(path).expect("Failed to read input file");
+    let reader = BufReader::new(file);
+
+    // lines that aren't valid UTF-8 are skipped, but reading stops at any other error,
+    // which would otherwise be returned again for every line after it
+    reader
+        .lines()
+        .map_while(|line| match line {
+            Ok(line) => Some(Some(line)),
+            Err(error) if error.kind() == ErrorKind::InvalidData => Some(None),
+            Err(_) => None,
+        })
+        .flatten()
+        .collect_vec()
+}
+
+fn ngrams_name(n: usize) -> String {
+    match n {
+        1 => "unigrams".to_string(),
+        2 => "bigrams".to_string(),
+        3 => "trigrams".to_string(),
+        _ => format!("{}-grams", n),
+    }
 }
 
-fn write_ps_to_csv<K>(path: &str, prefix: &str, hashmap: HashMap<K, f32>)
+fn write_ps_to_csv<K>(path: &str, prefix: &str, probabilities: impl Iterator<Item = (K, f64)>)
 where
     K: std::fmt::Debug,
 {
@@ -64,7 +308,7 @@ where
         .write_all("w,p(w)\n".as_bytes())
         .expect("Failed to write to target file");
 
-    for (gram, p) in hashmap {
+    for (gram, p) in probabilities {
         target_file
             .write_all(format!("{:?},{}\n", gram, p).as_bytes())
             .expect("Failed to write to target file");
@@ -0,0 +1,843 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::counts::{ends_with_sentence_start, NgramCounts};
+use crate::table::NgramTable;
+use crate::vocabulary::WordId;
+use crate::{GoodTuring, Options, Smoothing};
+use smoothing::{
+    good_turing_mass, history_stats, simple_good_turing, HistoryStats, KatzBackoff, KneserNeyCounts,
+};
+
+mod smoothing;
+
+/// An n-gram language model of a fixed order, estimated from the counts of a corpus.
+///
+/// The counts of every order from 1 up to `order` are kept, so the model can be
+/// asked for the probability of any n-gram up to that length. They are either owned
+/// by the model or shared between several models smoothing them differently.
+pub struct NgramModel<'a> {
+    counts: Cow<'a, NgramCounts>,
+    // simple_good_turing[n - 1] holds the smoothed count of every count of n-grams
+    simple_good_turing: Option<Vec<HashMap<u32, f64>>>,
+    // good_turing_mass[n - 2] holds the smoothed counts of the n-grams seen after
+    // every (n - 1)-word history, summed, for Simple Good-Turing
+    good_turing_mass: Option<Vec<NgramTable<f64>>>,
+    // history_stats[n - 1] holds the stats of the (n - 1)-word histories,
+    // for the smoothing methods that need them
+    history_stats: Option<Vec<NgramTable<HistoryStats>>>,
+    kneser_ney: Option<KneserNeyCounts>,
+    katz: Option<KatzBackoff>,
+    // interpolation_weights[n - 1] holds the weight of the n-gram estimates
+    interpolation_weights: Vec<f64>,
+    options: Options,
+}
+
+impl NgramModel<'static> {
+    pub fn train(
+        corpus: &[String],          // lines
+        vocabulary: &[Vec<String>], // optional extra n-grams to compute probabilities for
+        order: usize,
+        options: Options,
+    ) -> NgramModel<'static> {
+        let counts = NgramCounts::new(corpus, vocabulary, order, &options);
+
+        NgramModel::with_counts(Cow::Owned(counts), options)
+    }
+}
+
+impl<'a> NgramModel<'a> {
+    /// Smooth counts gathered beforehand, so that they are only counted once for
+    /// every smoothing method. The counting options, like sentence boundaries and
+    /// the closed vocabulary, are the ones the counts were gathered with.
+    ///
+    /// Panics if the tables the model derives from counts spilled to disk
+    /// can't be written to temporary files.
+    pub fn from_counts(counts: &'a NgramCounts, options: Options) -> NgramModel<'a> {
+        NgramModel::with_counts(Cow::Borrowed(counts), options)
+    }
+
+    fn with_counts(counts: Cow<'a, NgramCounts>, options: Options) -> NgramModel<'a> {
+        const WRITE_ERROR: &str = "Failed to write the tables of the model to a temporary file";
+
+        let order = counts.order;
+
+        let simple_good_turing: Option<Vec<HashMap<u32, f64>>> = match options.good_turing {
+            GoodTuring::Simple => Some(
+                counts
+                    .counts_of_counts
+                    .iter()
+                    .map(simple_good_turing)
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let good_turing_mass = simple_good_turing.as_ref().map(|smoothed_counts| {
+            (2..=order)
+                .map(|n| {
+                    good_turing_mass(
+                        n,
+                        counts.storage,
+                        counts.ngrams[n - 1].iter(),
+                        &smoothed_counts[n - 1],
+                    )
+                })
+                .collect::<Result<_, _>>()
+                .expect(WRITE_ERROR)
+        });
+
+        let history_stats = match (&options.smoothing, options.good_turing) {
+            (
+                Smoothing::Katz | Smoothing::WittenBell | Smoothing::AbsoluteDiscounting { .. },
+                _,
+            )
+            | (_, GoodTuring::Simple) => Some(
+                (1..=order)
+                    .map(|n| history_stats(n, counts.storage, counts.ngrams[n - 1].iter()))
+                    .collect::<Result<_, _>>()
+                    .expect(WRITE_ERROR),
+            ),
+            _ => None,
+        };
+
+        let kneser_ney = match options.smoothing {
+            Smoothing::KneserNey { discount } => Some(
+                KneserNeyCounts::new(&counts.ngrams, Some(discount), counts.storage)
+                    .expect(WRITE_ERROR),
+            ),
+            Smoothing::ModifiedKneserNey => {
+                Some(KneserNeyCounts::new(&counts.ngrams, None, counts.storage).expect(WRITE_ERROR))
+            }
+            _ => None,
+        };
+
+        let interpolation_weights = match &options.smoothing {
+            Smoothing::JelinekMercer { weights } if weights.is_empty() => {
+                vec![1.0 / order as f64; order]
+            }
+            Smoothing::JelinekMercer { weights } => {
+                assert_eq!(
+                    order,
+                    weights.len(),
+                    "There must be an interpolation weight for every order"
+                );
+
+                let total = weights.iter().sum::<f64>();
+                weights.iter().map(|weight| weight / total).collect_vec()
+            }
+            _ => vec![],
+        };
+
+        let mut model = NgramModel {
+            counts,
+            simple_good_turing,
+            good_turing_mass,
+            history_stats,
+            kneser_ney,
+            katz: None,
+            interpolation_weights,
+            options,
+        };
+
+        // the backoff weights are computed from the probabilities of the trained model itself
+        if let Smoothing::Katz = model.options.smoothing {
+            model.katz = Some(KatzBackoff::new(&model).expect(WRITE_ERROR));
+        }
+
+        model
+    }
+
+    /// The counts the model estimates its probabilities from.
+    pub fn counts(&self) -> &NgramCounts {
+        &self.counts
+    }
+
+    pub fn order(&self) -> usize {
+        self.counts.order()
+    }
+
+    /// Whether the word is part of the vocabulary, which a closed vocabulary
+    /// always is, since it maps unknown words to `<UNK>`.
+    pub fn knows(&self, word: &str) -> bool {
+        self.counts.knows(word)
+    }
+
+    /// How many times the n-gram was seen in the corpus.
+    pub fn count(&self, gram: &[String]) -> u32 {
+        self.counts.count(gram)
+    }
+
+    /// The natural logarithm of the probability of the last word of the n-gram
+    /// given the words before it, which is negative infinity for impossible n-grams.
+    ///
+    /// Panics if the n-gram is empty or longer than the order of the model.
+    pub fn log_prob(&self, gram: &[String]) -> f64 {
+        self.prob(gram).ln()
+    }
+
+    /// The probability of the last word of the n-gram given the words before it.
+    ///
+    /// Panics if the n-gram is empty or longer than the order of the model.
+    pub fn prob(&self, gram: &[String]) -> f64 {
+        self.prob_ids(&self.counts.vocabulary.ids(gram))
+    }
+
+    /// The probability of the last word of the n-gram of word IDs given the words before it.
+    ///
+    /// Panics if the n-gram is empty or longer than the order of the model.
+    pub fn prob_ids(&self, gram: &[WordId]) -> f64 {
+        let n = gram.len();
+        assert!(
+            n > 0 && n <= self.order(),
+            "Can only compute probabilities of 1 to {}-grams",
+            self.order()
+        );
+
+        if ends_with_sentence_start(gram) {
+            return 0.0;
+        }
+
+        match self.options.smoothing {
+            Smoothing::None if n > 1 && self.options.good_turing == GoodTuring::Simple => {
+                self.simple_good_turing_probability(gram)
+            }
+            Smoothing::None => self.additive_probability(gram),
+            Smoothing::KneserNey { .. } | Smoothing::ModifiedKneserNey => {
+                self.kneser_ney_probability(gram)
+            }
+            Smoothing::Katz => self.katz_probability(
+                self.katz
+                    .as_ref()
+                    .expect("Katz backoff weights are computed when training with Katz smoothing"),
+                gram,
+            ),
+            Smoothing::WittenBell => self.witten_bell_probability(gram),
+            Smoothing::JelinekMercer { .. } => self.jelinek_mercer_probability(gram),
+            Smoothing::StupidBackoff { factor } => self.stupid_backoff_score(gram, factor),
+            Smoothing::AbsoluteDiscounting { discount } => {
+                self.absolute_discounting_probability(gram, discount)
+            }
+        }
+    }
+
+    fn history_stats(&self, n: usize) -> &NgramTable<HistoryStats> {
+        &self
+            .history_stats
+            .as_ref()
+            .expect("History stats are gathered when training with a smoothing method using them")
+            [n - 1]
+    }
+
+    // maximum likelihood estimate with optional add-k smoothing and Good-Turing counts
+    fn additive_probability(&self, gram: &[WordId]) -> f64 {
+        let n = gram.len();
+        let smoothing = self.options.add_k_smoothing;
+        let count = self.counts.count_ids(gram);
+
+        // smooth count if good turing is enabled
+        let c: f64 = match self.options.good_turing {
+            GoodTuring::Off => count as f64,
+            GoodTuring::Simple if count > 0 => {
+                let smoothed_counts = &self.simple_good_turing.as_ref().unwrap()[n - 1];
+
+                *smoothed_counts.get(&count).unwrap_or(&0.0)
+            }
+            // unseen n-grams get the same mass from both estimators
+            GoodTuring::Basic | GoodTuring::Simple => {
+                let counts_of_counts = &self.counts.counts_of_counts[n - 1];
+                let c_1 = count + 1;
+                let n_1 = *counts_of_counts.get(&count).unwrap_or(&0) as f64;
+                let n_2 = *counts_of_counts.get(&c_1).unwrap_or(&0) as f64;
+
+                match count {
+                    0 => n_2,
+                    _ => c_1 as f64 * (n_2 / n_1),
+                }
+            }
+        };
+
+        // unigrams are relative to all words, longer n-grams to their history
+        let history_count = self.counts.count_ids(&gram[..n - 1]);
+        let denominator = history_count as f64 + smoothing * self.counts.vocabulary_size as f64;
+
+        match denominator > 0.0 {
+            true => (c + smoothing) / denominator,
+            false => 0.0,
+        }
+    }
+
+    /// Pick the add-k smoothing out of the candidates that gives a held-out corpus
+    /// the highest likelihood, and keep using it.
+    ///
+    /// Panics if there are no candidates, or if the model uses a smoothing method
+    /// other than additive smoothing, which doesn't add k to the counts.
+    pub fn learn_add_k_smoothing(&mut self, heldout: &[String], candidates: &[f64]) -> f64 {
+        assert!(
+            !candidates.is_empty(),
+            "There must be at least one candidate for add-k smoothing"
+        );
+        assert!(
+            matches!(self.options.smoothing, Smoothing::None),
+            "Add-k smoothing can only be learned for models without another smoothing method"
+        );
+
+        let grams = heldout
+            .iter()
+            .flat_map(|sentence| self.sentence_ngrams(sentence))
+            .map(|gram| self.counts.vocabulary.ids(&gram))
+            .collect_vec();
+
+        let (best_k, _log_likelihood) = candidates
+            .iter()
+            .map(|k| {
+                self.options.add_k_smoothing = *k;
+
+                let log_likelihood = grams
+                    .iter()
+                    .map(|gram| self.prob_ids(gram).ln())
+                    .sum::<f64>();
+
+                (*k, log_likelihood)
+            })
+            // the first candidate is kept when none gives the corpus any likelihood
+            .reduce(|best, candidate| match candidate.1 > best.1 {
+                true => candidate,
+                false => best,
+            })
+            .unwrap();
+
+        self.options.add_k_smoothing = best_k;
+
+        best_k
+    }
+
+    /// The probabilities of every known n-gram of the given order.
+    pub fn probabilities(&self, n: usize) -> HashMap<Vec<String>, f64> {
+        self.iter_probabilities(n).collect()
+    }
+
+    /// The probabilities of every known n-gram of the given order one at a time,
+    /// so that they are never all held in memory.
+    pub fn iter_probabilities(&self, n: usize) -> impl Iterator<Item = (Vec<String>, f64)> + '_ {
+        self.counts
+            .ngrams(n)
+            .map(|(gram, _count)| (self.counts.vocabulary.words(&gram), self.prob_ids(&gram)))
+    }
+
+    /// The probability of a whole sentence, where every word is conditioned on
+    /// as many of the words before it as the order of the model allows.
+    pub fn sentence_prob(&self, sentence: &str) -> f64 {
+        self.sentence_log_prob(sentence).exp()
+    }
+
+    /// The natural logarithm of the probability of a whole sentence, which is summed
+    /// in log space so that long sentences don't underflow.
+    pub fn sentence_log_prob(&self, sentence: &str) -> f64 {
+        self.sentence_ngrams(sentence)
+            .iter()
+            .map(|gram| self.log_prob(gram))
+            .sum()
+    }
+
+    /// The n-grams a sentence is scored by: every word along with
+    /// as many of the words before it as the order of the model allows.
+    pub fn sentence_ngrams(&self, sentence: &str) -> Vec<Vec<String>> {
+        self.counts.sentence_ngrams(sentence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::*;
+
+    fn to_gram(words: &str) -> Vec<String> {
+        words.split(' ').map(|s| s.to_string()).collect_vec()
+    }
+
+    fn get_test_corpus() -> Vec<String> {
+        [
+            "chicago is cold",
+            "chicago is cold",
+            "chicago is hot",
+            "africa is hot",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect_vec()
+    }
+
+    fn get_cold_corpus() -> Vec<String> {
+        [
+            "chicago is cold",
+            "chicago is cold",
+            "is cold",
+            "is cold",
+            "is cold",
+            "is cold",
+            "chicago is",
+            "chicago is",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect_vec()
+    }
+
+    // small enough to work out smoothed probabilities by hand, with sentence boundaries
+    fn get_boundaries_corpus() -> Vec<String> {
+        ["a b", "b", "a a b", "c b a", "a b", "b c"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect_vec()
+    }
+
+    // every history distributes all of its probability over the words, whether it was seen or not
+    fn assert_normalized(model: &NgramModel, histories: &[&str], words: &[&str]) {
+        for history in histories {
+            let total: f64 = words
+                .iter()
+                .map(|word| model.prob(&to_gram(&format!("{} {}", history, word))))
+                .sum();
+
+            assert!((1.0 - total).abs() < 1e-6, "{} sums to {}", history, total);
+        }
+    }
+
+    #[test]
+    fn test_trigrams() {
+        let model = NgramModel::train(&get_test_corpus(), &[], 3, Options::new());
+
+        assert_eq!(3, model.order());
+        assert_eq!(3, model.count(&to_gram("chicago is")));
+        assert_eq!(2.0 / 3.0, model.prob(&to_gram("chicago is cold")));
+        assert_eq!(1.0, model.prob(&to_gram("africa is hot")));
+        assert_eq!(0.0, model.prob(&to_gram("africa is cold")));
+    }
+
+    #[test]
+    fn test_lower_orders() {
+        let model = NgramModel::train(&get_test_corpus(), &[], 3, Options::new());
+
+        assert_eq!(4.0 / 12.0, model.prob(&to_gram("is")));
+        assert_eq!(0.5, model.prob(&to_gram("is hot")));
+        assert_eq!(3, model.probabilities(3).len());
+    }
+
+    #[test]
+    fn test_extra_vocabulary() {
+        let vocabulary = vec![to_gram("africa is cold"), to_gram("is warm")];
+
+        let model = NgramModel::train(&get_test_corpus(), &vocabulary, 3, Options::new());
+        let trigrams = model.probabilities(3);
+
+        assert_eq!(4, trigrams.len());
+        assert_eq!(0.0, trigrams[&to_gram("africa is cold")]);
+        assert_eq!(0.0, model.prob(&to_gram("warm")));
+    }
+
+    #[test]
+    fn test_unigrams_with_unseen_word() {
+        let model = NgramModel::train(&get_cold_corpus(), &[to_gram("hot")], 1, Options::new());
+        let unigrams = model.probabilities(1);
+
+        assert_eq!(4, unigrams.len());
+        assert_eq!(4.0 / 18.0, unigrams[&to_gram("chicago")]);
+        assert_eq!(8.0 / 18.0, unigrams[&to_gram("is")]);
+        assert_eq!(6.0 / 18.0, unigrams[&to_gram("cold")]);
+        assert_eq!(0.0, unigrams[&to_gram("hot")]);
+    }
+
+    #[test]
+    fn test_bigrams_with_unseen_pair() {
+        let model = NgramModel::train(&get_cold_corpus(), &[to_gram("is hot")], 2, Options::new());
+        let bigrams = model.probabilities(2);
+
+        assert_eq!(3, bigrams.len());
+        assert_eq!(1.0, bigrams[&to_gram("chicago is")]);
+        assert_eq!(6.0 / 8.0, bigrams[&to_gram("is cold")]);
+        assert_eq!(0.0, bigrams[&to_gram("is hot")]);
+    }
+
+    #[test]
+    fn test_sentence_boundaries() {
+        let corpus = ["a b", "b", "a a b"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect_vec();
+
+        let model = NgramModel::train(
+            &corpus,
+            &[],
+            2,
+            Options::new().with_sentence_boundaries(true),
+        );
+
+        assert_eq!(2.0 / 3.0, model.prob(&to_gram("<s> a")));
+        assert_eq!(1.0, model.prob(&to_gram("b </s>")));
+        assert_eq!(0.0, model.prob(&to_gram("<s>")));
+        assert_eq!(3.0 / 9.0, model.prob(&to_gram("</s>")));
+
+        assert_normalized(&model, &["<s>", "a", "b"], &["a", "b", "</s>"]);
+
+        let expected = (2.0 / 3.0) * (2.0 / 3.0) * 1.0;
+        assert!((expected - model.sentence_prob("a b")).abs() < 1e-6);
+        assert!((expected.ln() - model.sentence_log_prob("a b")).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unknown_words() {
+        let model = NgramModel::train(&get_test_corpus(), &[], 2, Options::new().with_min_count(2));
+
+        // "africa" is only seen once, so it is counted as <UNK>
+        assert_eq!(1, model.count(&to_gram("<UNK>")));
+        assert_eq!(1, model.count(&to_gram("africa")));
+        assert_eq!(1, model.count(&to_gram("tokyo")));
+        assert_eq!(1.0, model.prob(&to_gram("tokyo is")));
+        assert_eq!(model.prob(&to_gram("<UNK>")), model.prob(&to_gram("tokyo")));
+        assert!(model.sentence_prob("tokyo is hot") > 0.0);
+    }
+
+    #[test]
+    fn test_max_vocabulary_size() {
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            1,
+            Options::new().with_max_vocabulary_size(2),
+        );
+        let unigrams = model.probabilities(1);
+
+        // "is" and "chicago" are the most frequent words
+        assert_eq!(3, unigrams.len());
+        assert_eq!(5.0 / 12.0, unigrams[&to_gram("<UNK>")]);
+    }
+
+    #[test]
+    fn test_kneser_ney() {
+        let model = NgramModel::train(
+            &get_boundaries_corpus(),
+            &[],
+            3,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_kneser_ney(0.75),
+        );
+
+        // the unigrams use continuation counts: out of 11 distinct pairs, "b" follows three
+        // distinct words (<s>, "a" and "c") and "c" two, though "b" was seen 6 times,
+        // and the discount of the 4 words is spread uniformly over them
+        assert!((3.0 / 11.0 - model.prob(&to_gram("b"))).abs() < 1e-6);
+        assert!((2.0 / 11.0 - model.prob(&to_gram("c"))).abs() < 1e-6);
+
+        // "a b" follows two distinct words, "a a" and "a </s>" one each,
+        // and the bigram of "a b" interpolates with the unigram of "b"
+        let expected = (2.0 - 0.75) / 4.0 + (0.75 * 3.0 / 4.0) * (3.0 / 11.0);
+        assert!((expected - model.prob(&to_gram("a b"))).abs() < 1e-6);
+        assert!(model.prob(&to_gram("c a b")) > 0.0);
+
+        assert_normalized(
+            &model,
+            &["<s> a", "a a", "a b", "c b", "b c"],
+            &["a", "b", "c", "</s>"],
+        );
+    }
+
+    #[test]
+    fn test_empty_corpus() {
+        // every word is filtered out, so nothing is counted at all
+        let corpus = vec!["a b".to_string(), "".to_string()];
+        let smoothing: [fn(Options) -> Options; 4] = [
+            |options| options.with_kneser_ney(0.75),
+            |options| options.with_modified_kneser_ney(),
+            |options| options.with_witten_bell(),
+            |options| options.with_stupid_backoff(0.4),
+        ];
+
+        for smoothing in smoothing {
+            let options = Options::new()
+                .with_sentence_boundaries(true)
+                .with_token_filter(filters::MinLengthFilter::new(2));
+            let model = NgramModel::train(&corpus, &[], 2, smoothing(options));
+
+            assert_eq!(0.0, model.prob(&to_gram("a")));
+            assert_eq!(0.0, model.prob(&to_gram("a b")));
+        }
+    }
+
+    #[test]
+    fn test_modified_kneser_ney() {
+        let model = NgramModel::train(
+            &get_boundaries_corpus(),
+            &[],
+            2,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_modified_kneser_ney(),
+        );
+
+        // 7 bigrams are seen once, 1 twice, 2 three times and 1 four times,
+        // so Y = 7 / 9, D1 = 1 - 2Y / 7 = 7 / 9, D3+ = 3 - 4Y / 2 = 13 / 9,
+        // and D2 = 2 - 3Y * 2 falls back to Y, since it is negative
+        let (d1, d3) = (7.0 / 9.0, 13.0 / 9.0);
+
+        // "b" was followed by "</s>" 4 times, and by "a" and "c" once, and the continuation
+        // counts of the unigrams are too flat to estimate discounts from, so they use 0.5
+        let unigram = (2.0 - 0.5) / 11.0 + (0.5 * 4.0 / 11.0) / 4.0;
+        let expected = (1.0 - d1) / 6.0 + (d1 * 2.0 + d3) / 6.0 * unigram;
+        assert!((expected - model.prob(&to_gram("b c"))).abs() < 1e-6);
+
+        let expected = (4.0 - d3) / 6.0 + (d1 * 2.0 + d3) / 6.0 * (3.0 / 11.0);
+        assert!((expected - model.prob(&to_gram("b </s>"))).abs() < 1e-6);
+
+        assert_normalized(
+            &model,
+            &["<s>", "a", "b", "c", "d"],
+            &["a", "b", "c", "</s>"],
+        );
+    }
+
+    #[test]
+    fn test_katz_backoff() {
+        let model = NgramModel::train(
+            &get_boundaries_corpus(),
+            &[],
+            3,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_katz_backoff(),
+        );
+
+        // unseen pairs of seen words back off to the unigrams
+        assert_eq!(0, model.count(&to_gram("c c")));
+        assert!(model.prob(&to_gram("c c")) > 0.0);
+
+        assert_normalized(
+            &model,
+            &["<s>", "a", "b", "c", "<s> a", "a b", "b a", "c a"],
+            &["a", "b", "c", "</s>"],
+        );
+    }
+
+    #[test]
+    fn test_witten_bell() {
+        let model = NgramModel::train(
+            &get_boundaries_corpus(),
+            &[],
+            3,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_witten_bell(),
+        );
+
+        // "c" was seen after "b" once, and "b" was followed by 3 distinct words 6 times
+        let unigram = (2.0 + 4.0 / 4.0) / (19.0 + 4.0);
+        let expected = (1.0 + 3.0 * unigram) / (6.0 + 3.0);
+        assert!((expected - model.prob(&to_gram("b c"))).abs() < 1e-6);
+
+        assert_normalized(
+            &model,
+            &["<s>", "a", "b", "c", "d", "<s> a", "a b", "b a", "c a"],
+            &["a", "b", "c", "</s>"],
+        );
+    }
+
+    #[test]
+    fn test_jelinek_mercer() {
+        let model = NgramModel::train(
+            &get_boundaries_corpus(),
+            &[],
+            2,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_jelinek_mercer(&[1.0, 3.0]),
+        );
+
+        let expected = 0.25 * (2.0 / 19.0) + 0.75 * (1.0 / 6.0);
+        assert!((expected - model.prob(&to_gram("b c"))).abs() < 1e-6);
+
+        // an unseen history only uses the lower orders
+        let expected = 2.0 / 19.0;
+        assert!((expected - model.prob(&to_gram("d c"))).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_learn_interpolation_weights() {
+        let mut model = NgramModel::train(
+            &get_boundaries_corpus(),
+            &[],
+            2,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_jelinek_mercer(&[]),
+        );
+
+        // the bigrams of the held-out text were all seen in training
+        let heldout = vec!["a b".to_string(), "a b".to_string()];
+        let before = model.sentence_prob("a b");
+
+        model.learn_interpolation_weights(&heldout);
+        let weights = model.interpolation_weights();
+
+        assert!(weights[1] > weights[0]);
+        assert!((1.0 - weights.iter().sum::<f64>()).abs() < 1e-6);
+        assert!(model.sentence_prob("a b") > before);
+    }
+
+    #[test]
+    fn test_fractional_add_k_smoothing() {
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            3,
+            Options::new().with_add_k_smoothing(0.5),
+        );
+
+        // 5 words in the vocabulary
+        assert_eq!(2.5 / 5.5, model.prob(&to_gram("chicago is cold")));
+        assert_eq!(0.5 / 3.5, model.prob(&to_gram("africa is cold")));
+    }
+
+    #[test]
+    fn test_learn_add_k_smoothing() {
+        let mut model = NgramModel::train(&get_test_corpus(), &[], 2, Options::new());
+
+        // "is" was never seen after "cold", which takes a fair bit of smoothing
+        let heldout = vec!["cold is cold".to_string()];
+        let k = model.learn_add_k_smoothing(&heldout, &[0.01, 0.1, 1.0, 10.0]);
+
+        assert_eq!(1.0, k);
+        assert_eq!(3.0 / 9.0, model.prob(&to_gram("is cold")));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one candidate")]
+    fn test_learn_add_k_smoothing_without_candidates() {
+        let mut model = NgramModel::train(&get_test_corpus(), &[], 2, Options::new());
+
+        model.learn_add_k_smoothing(&["cold is cold".to_string()], &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "without another smoothing method")]
+    fn test_learn_add_k_smoothing_with_other_smoothing() {
+        let mut model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            2,
+            Options::new().with_witten_bell(),
+        );
+
+        model.learn_add_k_smoothing(&["cold is cold".to_string()], &[0.1, 1.0]);
+    }
+
+    #[test]
+    fn test_long_sentence_log_prob() {
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            2,
+            Options::new().with_add_k_smoothing(1.0),
+        );
+        let sentence = "chicago is cold africa is hot ".repeat(500);
+
+        // the probability underflows, the log probability doesn't
+        assert_eq!(0.0, model.sentence_prob(&sentence));
+        assert!(model.sentence_log_prob(&sentence).is_finite());
+        assert!(model.sentence_log_prob(&sentence) < -1000.0);
+    }
+
+    #[test]
+    fn test_stupid_backoff() {
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            3,
+            Options::new().with_stupid_backoff(0.4),
+        );
+
+        assert_eq!(2.0 / 3.0, model.prob(&to_gram("chicago is cold")));
+        // "is" was never seen after "africa" or "hot", but "cold" was seen after "is"
+        assert_eq!(0.4 * 0.5, model.prob(&to_gram("hot is cold")));
+        assert_eq!(
+            0.4 * (0.4 * (2.0 / 12.0)),
+            model.prob(&to_gram("hot hot cold"))
+        );
+    }
+
+    #[test]
+    fn test_absolute_discounting() {
+        let model = NgramModel::train(
+            &get_boundaries_corpus(),
+            &[],
+            2,
+            Options::new()
+                .with_sentence_boundaries(true)
+                .with_absolute_discounting(0.5),
+        );
+
+        // "b" was followed by 3 distinct words 6 times, once by "c"
+        let expected = (1.0 - 0.5) / 6.0 + (0.5 * 3.0 / 6.0) * (2.0 / 19.0);
+        assert!((expected - model.prob(&to_gram("b c"))).abs() < 1e-6);
+
+        assert_normalized(
+            &model,
+            &["<s>", "a", "b", "c", "d"],
+            &["a", "b", "c", "</s>"],
+        );
+    }
+
+    #[test]
+    fn test_trigrams_with_laplace_smoothing() {
+        let model = NgramModel::train(
+            &get_test_corpus(),
+            &[],
+            3,
+            Options::new().with_add_k_smoothing(1.0),
+        );
+
+        // 5 words in the vocabulary
+        assert_eq!(3.0 / 8.0, model.prob(&to_gram("chicago is cold")));
+        assert_eq!(1.0 / 6.0, model.prob(&to_gram("africa is cold")));
+    }
+
+    #[test]
+    fn test_smoothing_spilled_counts() {
+        let corpus = (0..3000)
+            .map(|i| format!("w{} w{} w{} w{}", i % 37, i % 11, i % 5, i * 7 % 13))
+            .collect_vec();
+        let options = || Options::new().with_sentence_boundaries(true);
+
+        let in_memory = NgramCounts::new(&corpus, &[], 3, &options());
+        let spilled = NgramCounts::new(&corpus, &[], 3, &options().with_memory_limit(50));
+        assert!(spilled.ngrams_in_memory() < in_memory.ngrams_in_memory() / 100);
+
+        let smoothing: [fn(Options) -> Options; 6] = [
+            |options| options.with_kneser_ney(0.75),
+            |options| options.with_modified_kneser_ney(),
+            |options| options.with_katz_backoff(),
+            |options| options.with_witten_bell(),
+            |options| options.with_absolute_discounting(0.75),
+            |options| options.with_simple_good_turing(true),
+        ];
+
+        // the tables derived from spilled counts are on disk, but give the same probabilities
+        for smoothing in smoothing {
+            let expected = NgramModel::from_counts(&in_memory, smoothing(options()));
+            let model = NgramModel::from_counts(&spilled, smoothing(options()));
+
+            for n in 1..=3 {
+                let expected = expected.probabilities(n);
+                let probabilities = model.probabilities(n);
+
+                assert_eq!(expected.len(), probabilities.len());
+                for (gram, p) in probabilities {
+                    assert!((expected[&gram] - p).abs() < 1e-9);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,718 @@
+use std::collections::HashMap;
+use std::io;
+
+use itertools::Itertools;
+
+use super::NgramModel;
+use crate::counts::ends_with_sentence_start;
+use crate::table::{NgramTable, Storage, TableBuilder, TableValue};
+use crate::vocabulary::{WordId, SENTENCE_START_ID};
+
+/// How much probability mass the words following a history have,
+/// and how many of them were seen once, twice or more often.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct HistoryStats {
+    pub total: u32,
+    pub once: u32,
+    pub twice: u32,
+    pub more: u32,
+}
+
+impl HistoryStats {
+    fn add(&mut self, count: u32) {
+        self.total += count;
+
+        match count {
+            0 => {}
+            1 => self.once += 1,
+            2 => self.twice += 1,
+            _ => self.more += 1,
+        }
+    }
+
+    pub fn distinct(&self) -> u32 {
+        self.once + self.twice + self.more
+    }
+}
+
+impl TableValue for HistoryStats {
+    const SIZE: usize = 16;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        for value in [self.total, self.once, self.twice, self.more] {
+            value.write(bytes);
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        HistoryStats {
+            total: u32::read(&bytes[0..]),
+            once: u32::read(&bytes[4..]),
+            twice: u32::read(&bytes[8..]),
+            more: u32::read(&bytes[12..]),
+        }
+    }
+}
+
+/// Group the counts of the n-grams of one order by their history, the n-gram without its
+/// last word. On disk, the counts have to come in sorted order, like a table gives them.
+pub(crate) fn history_stats<I>(
+    n: usize,
+    storage: Storage,
+    counts: I,
+) -> io::Result<NgramTable<HistoryStats>>
+where
+    I: Iterator<Item = (Vec<WordId>, u32)>,
+{
+    TableBuilder::group(
+        n - 1,
+        storage,
+        counts
+            .filter(|(gram, count)| *count > 0 && !ends_with_sentence_start(gram))
+            .map(|(gram, count)| (gram[..n - 1].to_vec(), count)),
+        HistoryStats::add,
+        |stats| stats,
+    )
+}
+
+/// Sum the Simple Good-Turing counts of the n-grams of one order by their history.
+/// On disk, the counts have to come in sorted order, like a table gives them.
+pub(crate) fn good_turing_mass<I>(
+    n: usize,
+    storage: Storage,
+    counts: I,
+    smoothed_counts: &HashMap<u32, f64>,
+) -> io::Result<NgramTable<f64>>
+where
+    I: Iterator<Item = (Vec<WordId>, u32)>,
+{
+    TableBuilder::group(
+        n - 1,
+        storage,
+        counts
+            .filter(|(gram, count)| *count > 0 && !ends_with_sentence_start(gram))
+            .map(|(gram, count)| (gram[..n - 1].to_vec(), count)),
+        |mass: &mut f64, count| *mass += smoothed_counts.get(&count).copied().unwrap_or(0.0),
+        |mass| mass,
+    )
+}
+
+fn starts_with_sentence_start(gram: &[WordId]) -> bool {
+    gram.first() == Some(&SENTENCE_START_ID)
+}
+
+/// The counts that interpolated Kneser-Ney estimates its probabilities from.
+///
+/// The highest order uses the plain counts, every lower order uses
+/// continuation counts: the number of distinct words seen before an n-gram.
+pub(crate) struct KneserNeyCounts {
+    // continuation_counts[n - 1] holds the continuation counts of n-grams below the highest order
+    continuation_counts: Vec<NgramTable<u32>>,
+    // history_stats[n - 1] holds the stats of the (n - 1)-word histories
+    history_stats: Vec<NgramTable<HistoryStats>>,
+    // discounts[n - 1] holds the discounts of n-grams seen once, twice, and three or more times
+    discounts: Vec<[f64; 3]>,
+}
+
+impl KneserNeyCounts {
+    /// Without a fixed discount, the three discounts of modified Kneser-Ney
+    /// are estimated from the counts of counts of every order.
+    pub fn new(
+        counts: &[NgramTable<u32>],
+        discount: Option<f64>,
+        storage: Storage,
+    ) -> io::Result<KneserNeyCounts> {
+        let order = counts.len();
+
+        let continuation_counts = (1..order)
+            .map(|n| {
+                TableBuilder::sum(
+                    n,
+                    storage,
+                    counts[n]
+                        .iter()
+                        .filter(|(_gram, count)| *count > 0)
+                        .map(|(gram, _count)| (gram[1..].to_vec(), 1)),
+                )
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut kneser_ney_counts = KneserNeyCounts {
+            continuation_counts,
+            history_stats: vec![],
+            discounts: vec![],
+        };
+
+        kneser_ney_counts.history_stats = (1..=order)
+            .map(|n| {
+                history_stats(
+                    n,
+                    storage,
+                    counts[n - 1].iter().map(|(gram, _count)| {
+                        let count = kneser_ney_counts.adjusted_count(counts, &gram);
+
+                        (gram, count)
+                    }),
+                )
+            })
+            .collect::<io::Result<_>>()?;
+
+        kneser_ney_counts.discounts = (1..=order)
+            .map(|n| match discount {
+                Some(discount) => [discount; 3],
+                None => modified_discounts(
+                    counts[n - 1]
+                        .iter()
+                        .map(|(gram, _count)| kneser_ney_counts.adjusted_count(counts, &gram)),
+                ),
+            })
+            .collect();
+
+        Ok(kneser_ney_counts)
+    }
+
+    // n-grams starting with <s> have no words before them, so they keep their plain counts
+    fn adjusted_count(&self, counts: &[NgramTable<u32>], gram: &[WordId]) -> u32 {
+        let n = gram.len();
+
+        match n == counts.len() || starts_with_sentence_start(gram) {
+            true => counts[n - 1].get(gram).unwrap_or(0),
+            false => self.continuation_counts[n - 1].get(gram).unwrap_or(0),
+        }
+    }
+
+    fn discount(&self, n: usize, count: u32) -> f64 {
+        match count {
+            0 => 0.0,
+            1 => self.discounts[n - 1][0],
+            2 => self.discounts[n - 1][1],
+            _ => self.discounts[n - 1][2],
+        }
+    }
+}
+
+/// Chen & Goodman's estimates of the discounts D1, D2 and D3+ from
+/// the number of n-grams seen one to four times.
+///
+/// On corpora too small to estimate a discount from, it falls back to the
+/// single discount n1 / (n1 + 2 * n2), or 0.5 when even that is unusable.
+fn modified_discounts<I>(counts: I) -> [f64; 3]
+where
+    I: Iterator<Item = u32>,
+{
+    let mut counts_of_counts = [0.0_f64; 5];
+    for count in counts.filter(|count| (1..=4).contains(count)) {
+        counts_of_counts[count as usize] += 1.0;
+    }
+
+    let [_, n1, n2, n3, n4] = counts_of_counts;
+    let y = n1 / (n1 + 2.0 * n2);
+    let single_discount = match y > 0.0 && y < 1.0 {
+        true => y,
+        false => 0.5,
+    };
+
+    [(1.0, n2 / n1), (2.0, n3 / n2), (3.0, n4 / n3)].map(|(count, ratio)| {
+        let discount = count - (count + 1.0) * y * ratio;
+
+        match discount > 0.0 && discount < count {
+            true => discount,
+            false => single_discount,
+        }
+    })
+}
+
+impl NgramModel<'_> {
+    // the probability every word gets from a uniform distribution over the vocabulary,
+    // which is 0 when no word was counted at all
+    fn uniform_probability(&self) -> f64 {
+        match self.counts.vocabulary_size {
+            0 => 0.0,
+            size => 1.0 / size as f64,
+        }
+    }
+
+    /// Simple Good-Turing for n-grams with a history: the seen n-grams get their smoothed
+    /// counts, and what is left of the count of the history is spread evenly over the words
+    /// never seen after it. If nothing is left, the seen n-grams share the whole count.
+    pub(super) fn simple_good_turing_probability(&self, gram: &[WordId]) -> f64 {
+        let n = gram.len();
+        let history = &gram[..n - 1];
+        let count = self.counts.count_ids(gram);
+        let smoothing = self.options.add_k_smoothing;
+
+        let stats = self.history_stats(n).get(history).unwrap_or_default();
+        let mass = self
+            .good_turing_mass
+            .as_ref()
+            .expect("Smoothed counts are summed when training with Simple Good-Turing")[n - 2]
+            .get(history)
+            .unwrap_or(0.0);
+        let total = stats.total as f64;
+        let unseen = self.counts.vocabulary_size - stats.distinct();
+        let spread = unseen > 0 && mass < total;
+
+        let c = match count {
+            0 if spread => (total - mass) / unseen as f64,
+            0 => 0.0,
+            _ => {
+                let smoothed = self.simple_good_turing.as_ref().unwrap()[n - 1]
+                    .get(&count)
+                    .copied()
+                    .unwrap_or(0.0);
+
+                match spread {
+                    true => smoothed,
+                    false => smoothed * total / mass,
+                }
+            }
+        };
+
+        let denominator = total + smoothing * self.counts.vocabulary_size as f64;
+
+        match denominator > 0.0 {
+            true => (c + smoothing) / denominator,
+            false => 0.0,
+        }
+    }
+
+    /// Interpolated (modified) Kneser-Ney, which bottoms out at a uniform distribution over the vocabulary.
+    pub(super) fn kneser_ney_probability(&self, gram: &[WordId]) -> f64 {
+        let kneser_ney = self
+            .kneser_ney
+            .as_ref()
+            .expect("Kneser-Ney counts are gathered when training with Kneser-Ney smoothing");
+
+        let n = gram.len();
+
+        let lower_order_probability = match n {
+            1 => self.uniform_probability(),
+            _ => self.kneser_ney_probability(&gram[1..]),
+        };
+
+        let history = &gram[..n - 1];
+        let stats = match kneser_ney.history_stats[n - 1].get(history) {
+            Some(stats) if stats.total > 0 => stats,
+            // an unseen history gives all of its mass to the lower order
+            _ => return lower_order_probability,
+        };
+
+        let count = kneser_ney.adjusted_count(&self.counts.ngrams, gram);
+        let discounted_count = (count as f64 - kneser_ney.discount(n, count)).max(0.0);
+
+        let interpolation_weight = (kneser_ney.discount(n, 1) * stats.once as f64
+            + kneser_ney.discount(n, 2) * stats.twice as f64
+            + kneser_ney.discount(n, 3) * stats.more as f64)
+            / stats.total as f64;
+
+        discounted_count / stats.total as f64 + interpolation_weight * lower_order_probability
+    }
+
+    /// Interpolated Witten-Bell, which gives the lower order as much weight as the number of
+    /// distinct words seen after the history, bottoming out at a uniform distribution.
+    ///
+    /// Histories are counted by the words seen after them,
+    /// which is the plain history count when sentence boundaries are on.
+    pub(super) fn witten_bell_probability(&self, gram: &[WordId]) -> f64 {
+        let n = gram.len();
+
+        let lower_order_probability = match n {
+            1 => self.uniform_probability(),
+            _ => self.witten_bell_probability(&gram[1..]),
+        };
+
+        let stats = match self.history_stats(n).get(&gram[..n - 1]) {
+            Some(stats) if stats.total > 0 => stats,
+            _ => return lower_order_probability,
+        };
+
+        let count = self.counts.ngrams[n - 1].get(gram).unwrap_or(0);
+        let distinct = stats.distinct() as f64;
+
+        (count as f64 + distinct * lower_order_probability) / (stats.total as f64 + distinct)
+    }
+
+    /// Jelinek-Mercer interpolation of the maximum likelihood estimates of every order.
+    ///
+    /// Orders with an unseen history are left out, and the weights of the rest are renormalized.
+    pub(super) fn jelinek_mercer_probability(&self, gram: &[WordId]) -> f64 {
+        let (probability, total_weight) = self
+            .interpolation_components(gram)
+            .into_iter()
+            .flatten()
+            .fold((0.0, 0.0), |(probability, total_weight), (weight, p)| {
+                (probability + weight * p, total_weight + weight)
+            });
+
+        match total_weight > 0.0 {
+            true => probability / total_weight,
+            false => 0.0,
+        }
+    }
+
+    // the weight and estimate of every order, or None when the history of that order is unseen
+    fn interpolation_components(&self, gram: &[WordId]) -> Vec<Option<(f64, f64)>> {
+        let n = gram.len();
+
+        (1..=n)
+            .map(|k| {
+                let gram = &gram[n - k..];
+
+                match k == 1 || self.counts.count_ids(&gram[..k - 1]) > 0 {
+                    true => Some((
+                        self.interpolation_weights[k - 1],
+                        self.additive_probability(gram),
+                    )),
+                    false => None,
+                }
+            })
+            .collect_vec()
+    }
+
+    pub fn interpolation_weights(&self) -> &[f64] {
+        &self.interpolation_weights
+    }
+
+    /// Learn the Jelinek-Mercer interpolation weights that maximize
+    /// the likelihood of a held-out corpus with expectation maximization.
+    pub fn learn_interpolation_weights(&mut self, heldout: &[String]) {
+        const MAX_ITERATIONS: usize = 100;
+        const TOLERANCE: f64 = 1e-5;
+
+        let grams = heldout
+            .iter()
+            .flat_map(|sentence| self.sentence_ngrams(sentence))
+            .map(|gram| self.counts.vocabulary.ids(&gram))
+            .collect_vec();
+
+        for _ in 0..MAX_ITERATIONS {
+            let mut expected_counts = vec![0.0_f64; self.counts.order];
+            let mut total = 0.0;
+
+            for gram in &grams {
+                let components = self.interpolation_components(gram);
+                let probability = components
+                    .iter()
+                    .flatten()
+                    .map(|(weight, p)| weight * p)
+                    .sum::<f64>();
+
+                if probability == 0.0 {
+                    continue;
+                }
+
+                // how responsible every order is for the probability of this n-gram
+                for (k, component) in components.iter().enumerate() {
+                    if let Some((weight, p)) = component {
+                        expected_counts[k] += weight * p / probability;
+                    }
+                }
+
+                total += 1.0;
+            }
+
+            if total == 0.0 {
+                return;
+            }
+
+            let weights = expected_counts
+                .iter()
+                .map(|count| count / total)
+                .collect_vec();
+            let change = weights
+                .iter()
+                .zip(&self.interpolation_weights)
+                .map(|(new, old)| (new - old).abs())
+                .fold(0.0, f64::max);
+
+            self.interpolation_weights = weights;
+
+            if change < TOLERANCE {
+                return;
+            }
+        }
+    }
+
+    /// Interpolated absolute discounting, which subtracts a fixed discount from every seen
+    /// n-gram and gives the freed mass to the lower orders, down to the plain unigrams.
+    pub(super) fn absolute_discounting_probability(&self, gram: &[WordId], discount: f64) -> f64 {
+        let n = gram.len();
+
+        if n == 1 {
+            return self.additive_probability(gram);
+        }
+
+        let lower_order_probability = self.absolute_discounting_probability(&gram[1..], discount);
+
+        let stats = match self.history_stats(n).get(&gram[..n - 1]) {
+            Some(stats) if stats.total > 0 => stats,
+            _ => return lower_order_probability,
+        };
+
+        let count = self.counts.ngrams[n - 1].get(gram).unwrap_or(0);
+        let discounted_count = (count as f64 - discount).max(0.0);
+        let interpolation_weight = discount * stats.distinct() as f64 / stats.total as f64;
+
+        discounted_count / stats.total as f64 + interpolation_weight * lower_order_probability
+    }
+
+    /// Brants et al.'s Stupid Backoff, the relative frequency of the n-gram, or the score
+    /// of the lower order times a fixed factor when the n-gram was never seen.
+    ///
+    /// The scores come straight from the counts and are not normalized probabilities.
+    pub(super) fn stupid_backoff_score(&self, gram: &[WordId], factor: f64) -> f64 {
+        let n = gram.len();
+        let count = self.counts.count_ids(gram);
+
+        match (n, count) {
+            // without any words, nothing has a score rather than 0 / 0
+            (1, _) if self.counts.total_words == 0 => 0.0,
+            (1, _) => count as f64 / self.counts.total_words as f64,
+            (_, 0) => factor * self.stupid_backoff_score(&gram[1..], factor),
+            _ => count as f64 / self.counts.count_ids(&gram[..n - 1]) as f64,
+        }
+    }
+
+    /// Katz backoff, with Good-Turing discounted estimates for seen n-grams, and the
+    /// weighted probability of the lower order for unseen ones, down to the plain unigrams.
+    pub(super) fn katz_probability(&self, katz: &KatzBackoff, gram: &[WordId]) -> f64 {
+        let n = gram.len();
+
+        if n == 1 {
+            return self.additive_probability(gram);
+        }
+
+        let history = &gram[..n - 1];
+        let stats = match self.history_stats(n).get(history) {
+            Some(stats) if stats.total > 0 => stats,
+            // an unseen history has no mass of its own to redistribute
+            _ => return self.katz_probability(katz, &gram[1..]),
+        };
+
+        match self.counts.ngrams[n - 1].get(gram) {
+            Some(count) if count > 0 => katz.discount(n, count) * count as f64 / stats.total as f64,
+            _ => {
+                let backoff_weight = katz.backoff_weights[n - 1].get(history).unwrap_or(1.0);
+
+                backoff_weight * self.katz_probability(katz, &gram[1..])
+            }
+        }
+    }
+}
+
+// Katz only discounts counts up to this threshold, higher counts are considered reliable
+const KATZ_THRESHOLD: u32 = 5;
+
+/// The discounts and backoff weights of a Katz backoff model.
+pub(crate) struct KatzBackoff {
+    // discounts[n - 1][r] holds the discount of n-grams seen r times, for r up to the threshold
+    discounts: Vec<Vec<f64>>,
+    // backoff_weights[n - 1] holds the alpha of every (n - 1)-word history
+    backoff_weights: Vec<NgramTable<f64>>,
+}
+
+impl KatzBackoff {
+    pub fn new(model: &NgramModel) -> io::Result<KatzBackoff> {
+        let mut katz = KatzBackoff {
+            discounts: model
+                .counts
+                .counts_of_counts
+                .iter()
+                .map(katz_discounts)
+                .collect(),
+            backoff_weights: vec![NgramTable::Memory(HashMap::new())],
+        };
+
+        // the weights of every order depend on the probabilities of the order below it
+        for n in 2..=model.counts.order {
+            let masses = model.counts.ngrams[n - 1]
+                .iter()
+                .filter(|(gram, count)| *count > 0 && !ends_with_sentence_start(gram))
+                .map(|(gram, _count)| {
+                    let seen = model.katz_probability(&katz, &gram);
+                    let lower_order = model.katz_probability(&katz, &gram[1..]);
+
+                    (gram[..n - 1].to_vec(), (seen, lower_order))
+                });
+
+            let backoff_weights = TableBuilder::group(
+                n - 1,
+                model.counts.storage,
+                masses,
+                |(seen_mass, lower_order_mass): &mut (f64, f64), (seen, lower_order)| {
+                    *seen_mass += seen;
+                    *lower_order_mass += lower_order;
+                },
+                |(seen_mass, lower_order_mass)| {
+                    // when the seen words take up all of the lower order mass, there is nothing left to back off to
+                    match lower_order_mass < 1.0 {
+                        true => ((1.0 - seen_mass) / (1.0 - lower_order_mass)).max(0.0),
+                        false => 0.0,
+                    }
+                },
+            )?;
+
+            katz.backoff_weights.push(backoff_weights);
+        }
+
+        Ok(katz)
+    }
+
+    fn discount(&self, n: usize, count: u32) -> f64 {
+        *self.discounts[n - 1].get(count as usize).unwrap_or(&1.0)
+    }
+}
+
+// Katz's discounts d_r = (r* / r - t) / (1 - t), where t = (k + 1) * N_(k+1) / N_1
+// and r* is the Good-Turing count. Discounts that can't be estimated are left at 1.
+fn katz_discounts(counts_of_counts: &HashMap<u32, u32>) -> Vec<f64> {
+    let n_r = |r: u32| *counts_of_counts.get(&r).unwrap_or(&0) as f64;
+
+    let k = KATZ_THRESHOLD as f64;
+    let t = (k + 1.0) * n_r(KATZ_THRESHOLD + 1) / n_r(1);
+
+    (0..=KATZ_THRESHOLD)
+        .map(|r| {
+            let good_turing_ratio = (r + 1) as f64 * n_r(r + 1) / n_r(r) / r as f64;
+            let discount = (good_turing_ratio - t) / (1.0 - t);
+
+            match r > 0 && discount > 0.0 && discount < 1.0 {
+                true => discount,
+                false => 1.0,
+            }
+        })
+        .collect()
+}
+
+/// Gale & Sampson's Simple Good-Turing estimate of the adjusted count of every seen count.
+///
+/// The counts of counts are smoothed with a line fitted to log Z_r against log r,
+/// and the raw Turing estimates are used for as long as they are significantly different
+/// from the smoothed ones. The adjusted counts are renormalized so that the seen n-grams
+/// leave N_1 for the unseen ones, like the plain Good-Turing estimate does.
+pub(crate) fn simple_good_turing(counts_of_counts: &HashMap<u32, u32>) -> HashMap<u32, f64> {
+    let counts = counts_of_counts
+        .iter()
+        .filter(|(count, times)| **count > 0 && **times > 0)
+        .map(|(count, times)| (*count, *times as f64))
+        .sorted_by_key(|(count, _times)| *count)
+        .collect_vec();
+
+    let n_r = |r: u32| *counts_of_counts.get(&r).unwrap_or(&0) as f64;
+
+    if counts.is_empty() {
+        return HashMap::new();
+    }
+
+    // Z_r averages N_r over the gap between its neighbouring counts
+    let log_z = counts
+        .iter()
+        .enumerate()
+        .map(|(i, (r, times))| {
+            let previous = match i {
+                0 => 0.0,
+                _ => counts[i - 1].0 as f64,
+            };
+            let next = match counts.get(i + 1) {
+                Some((next, _)) => *next as f64,
+                None => 2.0 * *r as f64 - previous,
+            };
+
+            ((*r as f64).ln(), (times / (0.5 * (next - previous))).ln())
+        })
+        .collect_vec();
+
+    let (intercept, slope) = least_squares(&log_z);
+    let smoothed = |r: f64| (intercept + slope * r.ln()).exp();
+
+    let mut use_smoothed = false;
+    let adjusted_counts = counts
+        .iter()
+        .map(|(r, times)| {
+            let r_1 = (r + 1) as f64;
+            let y = r_1 * smoothed(r_1) / smoothed(*r as f64);
+
+            if !use_smoothed && n_r(r + 1) > 0.0 {
+                let x = r_1 * n_r(r + 1) / times;
+                let deviation = 1.96
+                    * (r_1.powi(2) * n_r(r + 1) / times.powi(2) * (1.0 + n_r(r + 1) / times))
+                        .sqrt();
+
+                if (x - y).abs() > deviation {
+                    return (*r, x);
+                }
+            }
+
+            // once the smoothed estimate is used, it is used for all higher counts
+            use_smoothed = true;
+
+            (*r, y)
+        })
+        .collect_vec();
+
+    let total = counts
+        .iter()
+        .map(|(r, times)| *r as f64 * times)
+        .sum::<f64>();
+    let adjusted_total = adjusted_counts
+        .iter()
+        .map(|(r, adjusted)| adjusted * n_r(*r))
+        .sum::<f64>();
+    let normalization = (total - n_r(1)) / adjusted_total;
+
+    adjusted_counts
+        .into_iter()
+        .map(|(r, adjusted)| (r, adjusted * normalization))
+        .collect()
+}
+
+// The intercept and slope of the line through the points with the least squared error
+fn least_squares(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let covariance = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+    let variance = points
+        .iter()
+        .map(|(x, _)| (x - mean_x).powi(2))
+        .sum::<f64>();
+
+    let slope = match variance {
+        0.0 => 0.0,
+        _ => covariance / variance,
+    };
+
+    (mean_y - slope * mean_x, slope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modified_discounts() {
+        let counts = [(1, 10), (2, 5), (3, 3), (4, 2), (7, 4)]
+            .iter()
+            .flat_map(|(count, times)| std::iter::repeat_n(*count, *times));
+
+        let [d1, d2, d3] = modified_discounts(counts);
+
+        assert!((0.5 - d1).abs() < 1e-6);
+        assert!((1.1 - d2).abs() < 1e-6);
+        assert!((5.0 / 3.0 - d3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_modified_discounts_fall_back() {
+        // nothing was seen three or four times
+        let counts = [1, 1, 1, 2].into_iter();
+
+        for discount in modified_discounts(counts) {
+            assert!((0.6 - discount).abs() < 1e-6);
+        }
+    }
+}
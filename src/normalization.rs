@@ -0,0 +1,246 @@
+use std::borrow::Cow;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::tokenizer::Tokenizer;
+use crate::utils::{EMAIL, NUMBER, URL};
+
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum UnicodeForm {
+    #[default]
+    None,
+    Nfc,
+    Nfkc,
+}
+
+/// The steps that normalize text before and after it is split into words,
+/// every one of which is switched on through the `Options`.
+///
+/// Only lowercasing is on by default.
+#[derive(Default, Clone, Debug)]
+pub struct Normalizer {
+    pub(crate) preserve_case: bool,
+    pub(crate) unicode_form: UnicodeForm,
+    pub(crate) fold_quotes: bool,
+    pub(crate) strip_accents: bool,
+    pub(crate) mask_numbers: bool,
+    pub(crate) mask_urls: bool,
+    pub(crate) mask_emails: bool,
+}
+
+impl Normalizer {
+    /// The normalized words of a line, as split by the tokenizer.
+    ///
+    /// Masked URLs, email addresses and numbers are never split,
+    /// the tokenizer only sees the text between them.
+    pub fn words(&self, tokenizer: &dyn Tokenizer, line: &str) -> Vec<String> {
+        let line = self.normalize(line);
+
+        let mut words = vec![];
+        let mut start = 0;
+        for (mask_start, mask_end, mask) in self.masks(&line) {
+            words.extend(self.tokens(tokenizer, &line[start..mask_start]));
+            words.push(mask.to_string());
+
+            start = mask_end;
+        }
+        words.extend(self.tokens(tokenizer, &line[start..]));
+
+        words
+    }
+
+    /// The line with its characters normalized, before it is split into words.
+    pub fn normalize<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut line = Cow::Borrowed(line);
+
+        match self.unicode_form {
+            UnicodeForm::None => {}
+            UnicodeForm::Nfc => line = line.nfc().collect::<String>().into(),
+            UnicodeForm::Nfkc => line = line.nfkc().collect::<String>().into(),
+        }
+
+        if self.fold_quotes && line.contains(is_smart_quote) {
+            line = line.chars().map(fold_quote).collect::<String>().into();
+        }
+
+        // the base characters are composed again, so that only the accents are left out
+        if self.strip_accents {
+            line = line
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .nfc()
+                .collect::<String>()
+                .into();
+        }
+
+        line
+    }
+
+    fn tokens<'a>(
+        &'a self,
+        tokenizer: &dyn Tokenizer,
+        text: &str,
+    ) -> impl Iterator<Item = String> + 'a {
+        tokenizer
+            .tokenize(text)
+            .into_iter()
+            .map(|token| match self.preserve_case {
+                true => token,
+                false => token.to_lowercase(),
+            })
+    }
+
+    // The start, end and mask of every masked part of the line, in order.
+    // Where masks overlap, URLs go before email addresses, which go before numbers.
+    fn masks(&self, line: &str) -> Vec<(usize, usize, &'static str)> {
+        lazy_static! {
+            static ref URL_RE: Regex =
+                Regex::new(r#"(?i)\b(?:https?://|www\.)[^\s<>"]*[^\s<>".,;:!?)\]'’]"#).unwrap();
+            static ref EMAIL_RE: Regex = Regex::new(r"[\w.+-]+@[\w-]+(?:\.[\w-]+)*\.\w+").unwrap();
+            static ref NUMBER_RE: Regex = Regex::new(r"\b\d+(?:[.,]\d+)*\b").unwrap();
+        }
+
+        let masks = [
+            (self.mask_urls, &*URL_RE, URL),
+            (self.mask_emails, &*EMAIL_RE, EMAIL),
+            (self.mask_numbers, &*NUMBER_RE, NUMBER),
+        ];
+
+        let mut found = vec![];
+        let mut position = 0;
+        while let Some(next) = masks
+            .iter()
+            .filter(|(on, _, _)| *on)
+            .filter_map(|(_, regex, mask)| {
+                regex
+                    .find_at(line, position)
+                    .map(|found| (found.start(), found.end(), *mask))
+            })
+            .min_by_key(|(start, _, _)| *start)
+        {
+            found.push(next);
+            position = next.1;
+        }
+
+        found
+    }
+}
+
+fn is_smart_quote(c: char) -> bool {
+    fold_quote(c) != c
+}
+
+fn fold_quote(c: char) -> char {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::normalization::*;
+    use crate::tokenizer::{RegexTokenizer, WhitespaceTokenizer};
+
+    #[test]
+    fn test_default_normalization() {
+        let words = Normalizer::default().words(&RegexTokenizer::default(), "I didn't GO");
+
+        assert_eq!(vec!["i", "didn't", "go"], words);
+    }
+
+    #[test]
+    fn test_preserve_case() {
+        let normalizer = Normalizer {
+            preserve_case: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec!["I", "GO"],
+            normalizer.words(&RegexTokenizer::default(), "I GO")
+        );
+    }
+
+    #[test]
+    fn test_unicode_forms() {
+        // an e followed by a combining acute accent, and a ligature
+        let line = "cafe\u{301} \u{FB01}ne";
+
+        let nfc = Normalizer {
+            unicode_form: UnicodeForm::Nfc,
+            ..Default::default()
+        };
+        let nfkc = Normalizer {
+            unicode_form: UnicodeForm::Nfkc,
+            ..Default::default()
+        };
+
+        assert_eq!("café \u{FB01}ne", nfc.normalize(line));
+        assert_eq!("café fine", nfkc.normalize(line));
+    }
+
+    #[test]
+    fn test_fold_quotes() {
+        let normalizer = Normalizer {
+            fold_quotes: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec!["i", "didn't", "go"],
+            normalizer.words(&RegexTokenizer::default(), "I didn’t go")
+        );
+        assert_eq!("\"quoted\"", normalizer.normalize("“quoted”"));
+    }
+
+    #[test]
+    fn test_strip_accents() {
+        let normalizer = Normalizer {
+            strip_accents: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "Creme brulee a Sao Paulo",
+            normalizer.normalize("Crème brûlée à São Paulo")
+        );
+    }
+
+    #[test]
+    fn test_masks() {
+        let normalizer = Normalizer {
+            mask_numbers: true,
+            mask_urls: true,
+            mask_emails: true,
+            ..Default::default()
+        };
+
+        let words = normalizer.words(
+            &RegexTokenizer::default(),
+            "Mail jo.doe@example.com or see https://example.com/a?b=1, 3.5 of 22 win2",
+        );
+
+        assert_eq!(
+            vec!["mail", EMAIL, "or", "see", URL, NUMBER, "of", NUMBER, "win2"],
+            words
+        );
+    }
+
+    #[test]
+    fn test_masks_are_never_split() {
+        let normalizer = Normalizer {
+            mask_urls: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec!["go", "to", URL, ".", "now"],
+            normalizer.words(&WhitespaceTokenizer, "go to www.example.com/page. now")
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+// common English abbreviations, which are lowercased and written without their final period
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "mt", "ft", "vs", "etc", "al", "inc", "ltd",
+    "corp", "nos", "vol", "fig", "lt", "sgt", "capt", "gov", "sen", "rep", "ave", "blvd", "rd",
+    "dept", "approx", "jan", "feb", "apr", "jun", "jul", "aug", "sep", "sept", "oct", "nov",
+];
+
+// abbreviations that are words as well, like "no", which are only abbreviations before a number
+const NUMBERED_ABBREVIATIONS: &[&str] = &["no", "co", "est", "rev", "gen", "col", "mar", "dec"];
+
+// words that usually start a sentence, so that a single letter before them ends one,
+// like the A in "I got an A. Then I left.", instead of being an initial
+const SENTENCE_STARTERS: &[&str] = &[
+    "i", "a", "an", "the", "then", "this", "that", "these", "those", "it", "he", "she", "we",
+    "they", "you", "there", "here", "but", "and", "so", "if", "when", "after", "my", "his", "her",
+    "our", "their", "what", "why", "how", "now",
+];
+
+/// Splits text into sentences at periods, question marks and exclamation marks.
+///
+/// A period doesn't end a sentence after an abbreviation or an initial, unless the initial is
+/// followed by a word that usually starts a sentence, or an abbreviation that is a word as well,
+/// like `No.`, isn't followed by a number. Closing quotes and brackets stay with the sentence
+/// they close, and a sentence only starts with an uppercase letter or a digit. Sentences glued
+/// together without a space, like `a lion.That was`, are split as well.
+#[derive(Clone, Debug)]
+pub struct SentenceSplitter {
+    abbreviations: HashSet<String>,
+}
+
+impl Default for SentenceSplitter {
+    fn default() -> Self {
+        SentenceSplitter {
+            abbreviations: ABBREVIATIONS.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+}
+
+impl SentenceSplitter {
+    pub fn new() -> SentenceSplitter {
+        Default::default()
+    }
+
+    /// Add abbreviations to the built-in ones, with or without their final period.
+    pub fn with_abbreviations(mut self, abbreviations: &[&str]) -> Self {
+        self.abbreviations.extend(
+            abbreviations
+                .iter()
+                .map(|a| a.trim_end_matches('.').to_lowercase()),
+        );
+
+        self
+    }
+
+    /// The sentences of the text, without the whitespace around them.
+    pub fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let chars = text.char_indices().collect_vec();
+
+        let mut sentences = vec![];
+        let mut start = 0;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let (position, c) = chars[i];
+
+            if !is_terminator(c) {
+                i += 1;
+                continue;
+            }
+
+            // the whole run of terminators, along with the quotes and brackets it closes
+            let mut end = i + 1;
+            while end < chars.len() && is_terminator(chars[end].1) {
+                end += 1;
+            }
+            let single_period = c == '.' && end == i + 1;
+            while end < chars.len() && is_closing(chars[end].1) {
+                end += 1;
+            }
+
+            let word = word_before(&text[..position]);
+            let next = chars
+                .get(end)
+                .map_or("", |(position, _)| word_after(&text[*position..]));
+            let ends_sentence = !(single_period && self.is_abbreviation(word, next))
+                && match chars.get(end) {
+                    None => true,
+                    Some((_, next)) if next.is_whitespace() => chars[end..]
+                        .iter()
+                        .map(|(_, c)| *c)
+                        .find(|c| !c.is_whitespace() && !is_opening(*c))
+                        .is_none_or(starts_sentence),
+                    // a missing space, only after a lowercase word, to leave U.S.A alone
+                    Some((_, next)) => {
+                        next.is_uppercase()
+                            && word.chars().count() > 1
+                            && word.chars().last().is_some_and(char::is_lowercase)
+                    }
+                };
+
+            if ends_sentence {
+                let boundary = chars.get(end).map_or(text.len(), |(position, _)| *position);
+
+                sentences.push(text[start..boundary].trim());
+                start = boundary;
+            }
+
+            i = end;
+        }
+
+        sentences.push(text[start..].trim());
+        sentences.retain(|sentence| !sentence.is_empty());
+
+        sentences
+    }
+
+    // abbreviations, initials like the J in J. Smith, and letters with periods like U.S,
+    // depending on the word after them
+    fn is_abbreviation(&self, word: &str, next: &str) -> bool {
+        let word = word.to_lowercase();
+
+        if self.abbreviations.contains(&word) {
+            return true;
+        }
+        if NUMBERED_ABBREVIATIONS.contains(&word.as_str()) {
+            return next.starts_with(char::is_numeric);
+        }
+
+        is_single_letters(&word) && !is_sentence_starter(next)
+    }
+}
+
+fn is_single_letters(word: &str) -> bool {
+    word.split('.')
+        .all(|part| part.chars().count() == 1 && part.chars().all(char::is_alphabetic))
+}
+
+// The word that usually starts a sentence, rather than an initial like the I in J. I. Smith
+fn is_sentence_starter(word: &str) -> bool {
+    let bare = word.trim_end_matches(|c: char| !c.is_alphanumeric());
+
+    !(word.ends_with('.') && is_single_letters(bare))
+        && SENTENCE_STARTERS.contains(&bare.to_lowercase().as_str())
+}
+
+// The word right after whitespace, without the quotes and brackets that open it
+fn word_after(text: &str) -> &str {
+    let text = text.trim_start_matches(|c: char| c.is_whitespace() || is_opening(c));
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+
+    &text[..end]
+}
+
+// The word right before a position, without the quotes and brackets that open it
+fn word_before(text: &str) -> &str {
+    let start = text
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace() || is_opening(*c))
+        .map_or(0, |(position, c)| position + c.len_utf8());
+
+    &text[start..]
+}
+
+fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…')
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(c, '"' | '\'' | '”' | '’' | ')' | ']' | '»')
+}
+
+fn is_opening(c: char) -> bool {
+    matches!(c, '"' | '\'' | '“' | '‘' | '(' | '[' | '«')
+}
+
+fn starts_sentence(c: char) -> bool {
+    c.is_uppercase() || c.is_numeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sentences::*;
+
+    #[test]
+    fn test_split_sentences() {
+        let splitter = SentenceSplitter::new();
+
+        assert_eq!(
+            vec!["It is cold.", "Is it hot?", "No!"],
+            splitter.split("It is cold. Is it hot?  No!")
+        );
+        assert_eq!(vec!["It is cold"], splitter.split("It is cold"));
+        assert!(splitter.split("  ").is_empty());
+    }
+
+    #[test]
+    fn test_missing_spaces() {
+        let splitter = SentenceSplitter::new();
+
+        assert_eq!(
+            vec!["He was as brave as a lion.", "That was an obvious joke."],
+            splitter.split("He was as brave as a lion.That was an obvious joke.")
+        );
+        assert_eq!(
+            vec!["Born in the U.S.A. in 1949."],
+            splitter.split("Born in the U.S.A. in 1949.")
+        );
+        assert_eq!(
+            vec!["It costs 3.50 now."],
+            splitter.split("It costs 3.50 now.")
+        );
+    }
+
+    #[test]
+    fn test_abbreviations() {
+        let splitter = SentenceSplitter::new().with_abbreviations(&["approx.", "Cf."]);
+
+        assert_eq!(
+            vec!["Mr. Smith met Dr. J. Watson, e.g. at noon.", "Cf. Holmes."],
+            splitter.split("Mr. Smith met Dr. J. Watson, e.g. at noon. Cf. Holmes.")
+        );
+        // words like "no" are only abbreviations before a number
+        assert_eq!(
+            vec!["I said no.", "She left."],
+            splitter.split("I said no. She left.")
+        );
+        assert_eq!(
+            vec!["See No. 5 on Dec. 24, not in Mar.", "Then leave."],
+            splitter.split("See No. 5 on Dec. 24, not in Mar. Then leave.")
+        );
+    }
+
+    #[test]
+    fn test_initials() {
+        let splitter = SentenceSplitter::new();
+
+        assert_eq!(
+            vec!["I got an A.", "Then I left."],
+            splitter.split("I got an A. Then I left.")
+        );
+        assert_eq!(
+            vec!["It was an A.", "I left."],
+            splitter.split("It was an A. I left.")
+        );
+        assert_eq!(
+            vec![
+                "J. R. R. Tolkien and J. I. Packer met in the U.S.",
+                "The end."
+            ],
+            splitter.split("J. R. R. Tolkien and J. I. Packer met in the U.S. The end.")
+        );
+    }
+
+    #[test]
+    fn test_quotes() {
+        let splitter = SentenceSplitter::new();
+
+        assert_eq!(
+            vec![
+                "\"Stop!\" he said.",
+                "\"Why?\"",
+                "(It was late.)",
+                "She left..."
+            ],
+            splitter.split("\"Stop!\" he said. \"Why?\" (It was late.) She left...")
+        );
+    }
+}
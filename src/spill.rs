@@ -0,0 +1,204 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+use crate::counter::NgramCounter;
+use crate::vocabulary::WordId;
+
+type SortedCounts = Box<dyn Iterator<Item = io::Result<(Vec<WordId>, u32)>>>;
+
+/// Partial n-gram counts written to temporary files, each sorted by n-gram,
+/// so that corpora can be counted with a bounded number of n-grams in memory.
+///
+/// Merging them gives the counts in sorted order one at a time, to be written
+/// to tables on disk rather than collected in memory.
+///
+/// The files are deleted once they are dropped.
+#[derive(Default)]
+pub struct Spills {
+    files: Vec<File>,
+}
+
+impl Spills {
+    pub fn new() -> Spills {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Write the counts sorted by n-gram to a temporary file of their own.
+    pub fn spill(&mut self, counter: NgramCounter) -> io::Result<()> {
+        let mut file = tempfile::tempfile()?;
+
+        let mut writer = BufWriter::new(&mut file);
+        for (gram, count) in counter.into_sorted() {
+            write_count(&mut writer, &gram, count)?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        file.seek(SeekFrom::Start(0))?;
+        self.files.push(file);
+
+        Ok(())
+    }
+
+    /// Merge the spilled counts and the ones still in memory with a k-way merge,
+    /// giving every n-gram once, in sorted order, with the sum of its counts.
+    pub fn merge(self, rest: NgramCounter) -> MergedCounts {
+        let mut sources: Vec<SortedCounts> = self
+            .files
+            .into_iter()
+            .map(|file| {
+                let mut reader = BufReader::new(file);
+
+                Box::new(std::iter::from_fn(move || {
+                    read_count(&mut reader).transpose()
+                })) as SortedCounts
+            })
+            .collect();
+        sources.push(Box::new(rest.into_sorted().into_iter().map(Ok)));
+
+        MergedCounts {
+            sources,
+            heap: BinaryHeap::new(),
+            started: false,
+        }
+    }
+}
+
+/// The n-gram counts of several sorted sources, merged in sorted order.
+pub struct MergedCounts {
+    sources: Vec<SortedCounts>,
+    // the next n-gram of every source that isn't exhausted, smallest first
+    heap: BinaryHeap<Reverse<(Vec<WordId>, usize, u32)>>,
+    started: bool,
+}
+
+impl MergedCounts {
+    fn next_count(&mut self) -> io::Result<Option<(Vec<WordId>, u32)>> {
+        if !self.started {
+            self.started = true;
+
+            for source in 0..self.sources.len() {
+                self.refill(source)?;
+            }
+        }
+
+        let Some(Reverse((gram, source, mut count))) = self.heap.pop() else {
+            return Ok(None);
+        };
+        self.refill(source)?;
+
+        // the same n-gram can be in every source
+        while let Some(Reverse((next_gram, _, _))) = self.heap.peek() {
+            if *next_gram != gram {
+                break;
+            }
+
+            let Reverse((_, source, next_count)) = self.heap.pop().unwrap();
+            count += next_count;
+            self.refill(source)?;
+        }
+
+        Ok(Some((gram, count)))
+    }
+
+    fn refill(&mut self, source: usize) -> io::Result<()> {
+        if let Some(next) = self.sources[source].next() {
+            let (gram, count) = next?;
+
+            self.heap.push(Reverse((gram, source, count)));
+        }
+
+        Ok(())
+    }
+}
+
+impl Iterator for MergedCounts {
+    type Item = io::Result<(Vec<WordId>, u32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_count().transpose()
+    }
+}
+
+// every count is stored as the length of the n-gram, its word IDs, and the count itself
+fn write_count(writer: &mut impl Write, gram: &[WordId], count: u32) -> io::Result<()> {
+    writer.write_all(&(gram.len() as u32).to_le_bytes())?;
+    for id in gram {
+        writer.write_all(&id.to_le_bytes())?;
+    }
+    writer.write_all(&count.to_le_bytes())
+}
+
+fn read_count(reader: &mut impl Read) -> io::Result<Option<(Vec<WordId>, u32)>> {
+    let n = match read_u32(reader) {
+        Ok(n) => n,
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    let gram = (0..n)
+        .map(|_| read_u32(reader))
+        .collect::<io::Result<Vec<_>>>()?;
+    let count = read_u32(reader)?;
+
+    Ok(Some((gram, count)))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::counter::NgramCounter;
+    use crate::spill::*;
+
+    #[test]
+    fn test_merge_spills() {
+        let mut spills = Spills::new();
+
+        let mut counter = NgramCounter::new(2);
+        counter.add_sentence(&[3, 4, 5]);
+        spills.spill(counter).unwrap();
+
+        let mut counter = NgramCounter::new(2);
+        counter.add_sentence(&[4, 5]);
+        spills.spill(counter).unwrap();
+
+        let mut rest = NgramCounter::new(2);
+        rest.add_sentence(&[3]);
+
+        assert_eq!(2, spills.len());
+
+        let merged = spills.merge(rest).collect::<io::Result<Vec<_>>>().unwrap();
+        let expected = vec![
+            (vec![3], 2),
+            (vec![3, 4], 1),
+            (vec![4], 2),
+            (vec![4, 5], 2),
+            (vec![5], 2),
+        ];
+
+        assert_eq!(expected, merged);
+        assert!(merged
+            .iter()
+            .map(|(gram, _)| gram)
+            .tuple_windows()
+            .all(|(l, r)| l < r));
+    }
+}
@@ -0,0 +1,516 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use itertools::Itertools;
+
+use crate::counter::NgramCounter;
+use crate::spill::Spills;
+use crate::vocabulary::WordId;
+
+// How many records of a table on disk are read at once, only the first n-gram
+// of every block is kept in memory
+const BLOCK_RECORDS: usize = 512;
+
+/// Where the tables of counts and statistics are kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Storage {
+    Memory,
+    // in sorted temporary files, with at most about `memory_limit` n-grams
+    // in memory while a table is being sorted
+    Disk { memory_limit: usize },
+}
+
+/// A value stored along with every n-gram of a table, with a fixed size on disk.
+pub(crate) trait TableValue: Copy + Send + Sync + 'static {
+    const SIZE: usize;
+
+    fn write(&self, bytes: &mut Vec<u8>);
+    fn read(bytes: &[u8]) -> Self;
+}
+
+impl TableValue for u32 {
+    const SIZE: usize = 4;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        u32::from_le_bytes(bytes[..4].try_into().unwrap())
+    }
+}
+
+impl TableValue for f64 {
+    const SIZE: usize = 8;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        f64::from_le_bytes(bytes[..8].try_into().unwrap())
+    }
+}
+
+/// The n-grams of one order mapped to a value, like their counts.
+///
+/// Small tables are kept in memory. Large ones are written sorted to a temporary file,
+/// which is looked up a block of n-grams at a time, so that the memory they take is
+/// bounded by the number of blocks rather than the number of n-grams.
+#[derive(Clone, Debug)]
+pub(crate) enum NgramTable<V> {
+    Memory(HashMap<Vec<WordId>, V>),
+    // shared by every clone, since the file is never written again
+    Disk(Arc<DiskTable<V>>),
+}
+
+impl<V: TableValue> NgramTable<V> {
+    pub fn get(&self, gram: &[WordId]) -> Option<V> {
+        match self {
+            NgramTable::Memory(values) => values.get(gram).copied(),
+            NgramTable::Disk(table) => table
+                .get(gram)
+                .expect("Failed to read an n-gram table from a temporary file"),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            NgramTable::Memory(values) => values.len(),
+            NgramTable::Disk(table) => table.len,
+        }
+    }
+
+    /// Every n-gram with its value, in no particular order when the table is in memory.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Vec<WordId>, V)> + '_> {
+        match self {
+            NgramTable::Memory(values) => {
+                Box::new(values.iter().map(|(gram, value)| (gram.clone(), *value)))
+            }
+            NgramTable::Disk(table) => Box::new(table.iter()),
+        }
+    }
+
+    /// Every n-gram with its value, sorted by n-gram, so that n-grams
+    /// with the same history follow each other.
+    pub fn iter_sorted(&self) -> Box<dyn Iterator<Item = (Vec<WordId>, V)> + '_> {
+        match self {
+            NgramTable::Memory(values) => Box::new(
+                values
+                    .iter()
+                    .sorted_unstable_by(|(l_gram, _), (r_gram, _)| l_gram.cmp(r_gram))
+                    .map(|(gram, value)| (gram.clone(), *value)),
+            ),
+            NgramTable::Disk(table) => Box::new(table.iter()),
+        }
+    }
+
+    /// How many n-grams the table holds in memory.
+    pub fn in_memory(&self) -> usize {
+        match self {
+            NgramTable::Memory(values) => values.len(),
+            NgramTable::Disk(table) => table.index.len(),
+        }
+    }
+}
+
+impl<V: TableValue + PartialEq> PartialEq for NgramTable<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter_sorted().eq(other.iter_sorted())
+    }
+}
+
+/// Sorted records of an n-gram and its value in a temporary file,
+/// which is deleted once the table is dropped.
+#[derive(Debug)]
+pub(crate) struct DiskTable<V> {
+    file: File,
+    // the length of the n-grams
+    n: usize,
+    len: usize,
+    // the first n-gram of every block of records
+    index: Vec<Vec<WordId>>,
+    // the block that was read last, since lookups often hit the same block in a row
+    cache: Mutex<(usize, Vec<u8>)>,
+    value: PhantomData<V>,
+}
+
+impl<V: TableValue> DiskTable<V> {
+    fn record_size(&self) -> usize {
+        self.n * 4 + V::SIZE
+    }
+
+    fn get(&self, gram: &[WordId]) -> io::Result<Option<V>> {
+        if gram.len() != self.n {
+            return Ok(None);
+        }
+
+        // the block starting at the last n-gram not greater than this one
+        let block = match self.index.partition_point(|first| first.as_slice() <= gram) {
+            0 => return Ok(None),
+            blocks => blocks - 1,
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.0 != block || cache.1.is_empty() {
+            *cache = (block, self.read_block(block)?);
+        }
+
+        let records = cache.1.len() / self.record_size();
+        let record = |i: usize| &cache.1[i * self.record_size()..(i + 1) * self.record_size()];
+
+        // the records of the block are sorted by n-gram as well
+        let (mut low, mut high) = (0, records);
+        while low < high {
+            let middle = (low + high) / 2;
+
+            match self.ids(record(middle)).cmp(gram.iter().copied()) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Ok(Some(V::read(&record(middle)[self.n * 4..]))),
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Vec<WordId>, V)> + '_ {
+        (0..self.index.len()).flat_map(move |block| {
+            let bytes = self
+                .read_block(block)
+                .expect("Failed to read an n-gram table from a temporary file");
+
+            bytes
+                .chunks_exact(self.record_size())
+                .map(|record| (self.read_gram(record), V::read(&record[self.n * 4..])))
+                .collect_vec()
+        })
+    }
+
+    fn read_block(&self, block: usize) -> io::Result<Vec<u8>> {
+        let first = block * BLOCK_RECORDS;
+        let records = BLOCK_RECORDS.min(self.len - first);
+
+        let mut bytes = vec![0; records * self.record_size()];
+        read_exact_at(&self.file, &mut bytes, (first * self.record_size()) as u64)?;
+
+        Ok(bytes)
+    }
+
+    fn read_gram(&self, record: &[u8]) -> Vec<WordId> {
+        self.ids(record).collect()
+    }
+
+    fn ids<'r>(&self, record: &'r [u8]) -> impl Iterator<Item = WordId> + 'r {
+        record[..self.n * 4]
+            .chunks_exact(4)
+            .map(|id| WordId::from_le_bytes(id.try_into().unwrap()))
+    }
+}
+
+// blocks are read at an offset instead of seeking, so that tables can be
+// iterated over and looked up at the same time
+#[cfg(unix)]
+fn read_exact_at(file: &File, bytes: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+
+    file.read_exact_at(bytes, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut bytes: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !bytes.is_empty() {
+        match file.seek_read(bytes, offset)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            read => {
+                bytes = &mut bytes[read..];
+                offset += read as u64;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fills a table of n-grams of one order, in memory or on disk.
+pub(crate) enum TableBuilder<V> {
+    Memory(HashMap<Vec<WordId>, V>),
+    Disk(TableWriter<V>),
+}
+
+impl<V: TableValue> TableBuilder<V> {
+    pub fn new(n: usize, storage: Storage) -> io::Result<TableBuilder<V>> {
+        Ok(match storage {
+            Storage::Memory => TableBuilder::Memory(HashMap::new()),
+            Storage::Disk { .. } => TableBuilder::Disk(TableWriter::new(n)?),
+        })
+    }
+
+    /// On disk, the n-grams have to be pushed once each, in sorted order.
+    pub fn push(&mut self, gram: Vec<WordId>, value: V) -> io::Result<()> {
+        match self {
+            TableBuilder::Memory(values) => {
+                values.insert(gram, value);
+
+                Ok(())
+            }
+            TableBuilder::Disk(writer) => writer.push(&gram, value),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<NgramTable<V>> {
+        Ok(match self {
+            TableBuilder::Memory(values) => NgramTable::Memory(values),
+            TableBuilder::Disk(writer) => NgramTable::Disk(Arc::new(writer.finish()?)),
+        })
+    }
+
+    /// Combine the items of every n-gram into a single value. On disk, the items of
+    /// an n-gram have to follow each other, and the n-grams come in sorted order,
+    /// like the histories of the n-grams of a sorted table do.
+    pub fn group<I, T, A>(
+        n: usize,
+        storage: Storage,
+        items: I,
+        add: impl Fn(&mut A, T),
+        finish: impl Fn(A) -> V,
+    ) -> io::Result<NgramTable<V>>
+    where
+        I: Iterator<Item = (Vec<WordId>, T)>,
+        A: Default,
+    {
+        let mut table = TableBuilder::new(n, storage)?;
+
+        match &mut table {
+            TableBuilder::Memory(values) => {
+                let groups = items.fold(HashMap::new(), |mut groups, (gram, item)| {
+                    add(groups.entry(gram).or_default(), item);
+
+                    groups
+                });
+
+                values.extend(
+                    groups
+                        .into_iter()
+                        .map(|(gram, accumulated)| (gram, finish(accumulated))),
+                );
+            }
+            TableBuilder::Disk(writer) => {
+                let mut group: Option<(Vec<WordId>, A)> = None;
+
+                for (gram, item) in items {
+                    match &mut group {
+                        Some((current, accumulated)) if *current == gram => add(accumulated, item),
+                        _ => {
+                            let mut accumulated = A::default();
+                            add(&mut accumulated, item);
+
+                            if let Some((done, accumulated)) = group.replace((gram, accumulated)) {
+                                writer.push(&done, finish(accumulated))?;
+                            }
+                        }
+                    }
+                }
+
+                if let Some((done, accumulated)) = group {
+                    writer.push(&done, finish(accumulated))?;
+                }
+            }
+        }
+
+        table.finish()
+    }
+}
+
+impl TableBuilder<u32> {
+    /// Sum the counts of n-grams of one order that come in any order, sorting them
+    /// on disk through spilled counters when the table is stored on disk.
+    pub fn sum<I>(n: usize, storage: Storage, counts: I) -> io::Result<NgramTable<u32>>
+    where
+        I: Iterator<Item = (Vec<WordId>, u32)>,
+    {
+        match storage {
+            Storage::Memory => Ok(NgramTable::Memory(counts.fold(
+                HashMap::new(),
+                |mut sums, (gram, count)| {
+                    *sums.entry(gram).or_default() += count;
+
+                    sums
+                },
+            ))),
+            Storage::Disk { memory_limit } => {
+                let mut spills = Spills::new();
+                let mut counter = NgramCounter::new(n);
+
+                for (gram, count) in counts {
+                    counter.add(&gram, count);
+
+                    if counter.len() > memory_limit {
+                        spills.spill(mem::replace(&mut counter, NgramCounter::new(n)))?;
+                    }
+                }
+
+                let mut table = TableBuilder::new(n, storage)?;
+                for sum in spills.merge(counter) {
+                    let (gram, count) = sum?;
+
+                    table.push(gram, count)?;
+                }
+
+                table.finish()
+            }
+        }
+    }
+}
+
+/// Writes sorted records to a temporary file, keeping the first n-gram of every block.
+pub(crate) struct TableWriter<V> {
+    writer: BufWriter<File>,
+    n: usize,
+    len: usize,
+    index: Vec<Vec<WordId>>,
+    bytes: Vec<u8>,
+    value: PhantomData<V>,
+}
+
+impl<V: TableValue> TableWriter<V> {
+    fn new(n: usize) -> io::Result<TableWriter<V>> {
+        Ok(TableWriter {
+            writer: BufWriter::new(tempfile::tempfile()?),
+            n,
+            len: 0,
+            index: vec![],
+            bytes: vec![],
+            value: PhantomData,
+        })
+    }
+
+    fn push(&mut self, gram: &[WordId], value: V) -> io::Result<()> {
+        assert_eq!(
+            self.n,
+            gram.len(),
+            "A table only holds n-grams of one order"
+        );
+        debug_assert!(
+            !self.len.is_multiple_of(BLOCK_RECORDS)
+                || self.index.last().is_none_or(|last| **last < *gram),
+            "The n-grams of a table on disk have to be sorted"
+        );
+
+        if self.len.is_multiple_of(BLOCK_RECORDS) {
+            self.index.push(gram.to_vec());
+        }
+
+        self.bytes.clear();
+        for id in gram {
+            self.bytes.extend_from_slice(&id.to_le_bytes());
+        }
+        value.write(&mut self.bytes);
+
+        self.writer.write_all(&self.bytes)?;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<DiskTable<V>> {
+        let file = self
+            .writer
+            .into_inner()
+            .map_err(|error| error.into_error())?;
+
+        Ok(DiskTable {
+            file,
+            n: self.n,
+            len: self.len,
+            index: self.index,
+            cache: Mutex::new((0, vec![])),
+            value: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::table::*;
+
+    fn build(storage: Storage, grams: &[(Vec<WordId>, u32)]) -> NgramTable<u32> {
+        let mut table = TableBuilder::new(2, storage).unwrap();
+        for (gram, count) in grams {
+            table.push(gram.clone(), *count).unwrap();
+        }
+
+        table.finish().unwrap()
+    }
+
+    #[test]
+    fn test_table_on_disk() {
+        // enough n-grams for several blocks
+        let grams = (0..2000).map(|i| (vec![i / 7, i % 7 * 2], i)).collect_vec();
+
+        let in_memory = build(Storage::Memory, &grams);
+        let on_disk = build(Storage::Disk { memory_limit: 10 }, &grams);
+
+        assert_eq!(in_memory, on_disk);
+        assert_eq!(Some(1999), on_disk.get(&[285, 8]));
+        assert_eq!(Some(0), on_disk.get(&[0, 0]));
+        assert_eq!(Some(700), on_disk.get(&[100, 0]));
+        assert_eq!(None, on_disk.get(&[100, 1]));
+        assert_eq!(None, on_disk.get(&[300, 0]));
+        assert_eq!(None, on_disk.get(&[100]));
+        assert_eq!(4, on_disk.in_memory());
+    }
+
+    #[test]
+    fn test_sum_on_disk() {
+        let counts = (0..1000).map(|i| (vec![i % 13, i % 3], 1)).collect_vec();
+
+        let in_memory = TableBuilder::sum(2, Storage::Memory, counts.clone().into_iter()).unwrap();
+        let on_disk =
+            TableBuilder::sum(2, Storage::Disk { memory_limit: 10 }, counts.into_iter()).unwrap();
+
+        assert_eq!(in_memory, on_disk);
+        assert_eq!(39, on_disk.len());
+        assert_eq!(
+            1000,
+            on_disk.iter().map(|(_gram, count)| count).sum::<u32>()
+        );
+    }
+
+    #[test]
+    fn test_group_on_disk() {
+        // sorted, so that the bigrams of every history follow each other
+        let grams = (0..2000)
+            .map(|i| (vec![i / 10, i % 10], i % 3))
+            .collect_vec();
+        let histories = || {
+            grams
+                .iter()
+                .map(|(gram, count)| (gram[..1].to_vec(), *count))
+        };
+        let group = |storage| {
+            TableBuilder::group(
+                1,
+                storage,
+                histories(),
+                |total: &mut u32, count| *total += count,
+                |total| total as f64 / 10.0,
+            )
+            .unwrap()
+        };
+
+        let in_memory = group(Storage::Memory);
+        let on_disk = group(Storage::Disk { memory_limit: 10 });
+
+        assert_eq!(in_memory, on_disk);
+        assert_eq!(200, on_disk.len());
+        assert_eq!(Some(0.9), on_disk.get(&[0]));
+    }
+}
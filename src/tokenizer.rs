@@ -0,0 +1,233 @@
+use std::iter::once;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::utils::{EMAIL, NUMBER, URL, WORD_END, WORD_START};
+
+/// Splits a line of text into the tokens that are counted as words.
+///
+/// Tokens are normalized after they are split off, so a tokenizer only decides where
+/// words start and end. It is shared by the threads counting a corpus.
+pub trait Tokenizer: Send + Sync {
+    fn tokenize(&self, line: &str) -> Vec<String>;
+}
+
+/// Every match of a regular expression is a token,
+/// by default the runs of word characters and apostrophes.
+#[derive(Clone, Debug)]
+pub struct RegexTokenizer {
+    regex: Regex,
+}
+
+impl RegexTokenizer {
+    pub fn new(pattern: &str) -> Result<RegexTokenizer, regex::Error> {
+        Ok(RegexTokenizer {
+            regex: Regex::new(pattern)?,
+        })
+    }
+}
+
+impl Default for RegexTokenizer {
+    fn default() -> Self {
+        lazy_static! {
+            static ref WORDS_RE: Regex = Regex::new(r"[\w']+").unwrap();
+        }
+
+        RegexTokenizer {
+            regex: WORDS_RE.clone(),
+        }
+    }
+}
+
+impl Tokenizer for RegexTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        self.regex
+            .find_iter(line)
+            .map(|token| token.as_str().to_string())
+            .collect()
+    }
+}
+
+/// Every run of characters between whitespace is a token, punctuation included.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        line.split_whitespace()
+            .map(|token| token.to_string())
+            .collect()
+    }
+}
+
+/// The words between the Unicode word boundaries of UAX #29, which keeps contractions
+/// and decimal numbers together, splits hyphenated words and drops punctuation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnicodeWordTokenizer;
+
+impl Tokenizer for UnicodeWordTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        line.unicode_words().map(|word| word.to_string()).collect()
+    }
+}
+
+/// Words, including the ones joined by hyphens or apostrophes,
+/// with every punctuation mark kept as a token of its own.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PunctuationTokenizer;
+
+impl Tokenizer for PunctuationTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        lazy_static! {
+            static ref TOKENS_RE: Regex = Regex::new(r"\w+(?:[-'’]\w+)*|[^\w\s]").unwrap();
+        }
+
+        TOKENS_RE
+            .find_iter(line)
+            .map(|token| token.as_str().to_string())
+            .collect()
+    }
+}
+
+/// The characters of the words split off by another tokenizer, for character n-gram models.
+///
+/// Words are separated by a space, or with word boundaries, every word
+/// starts with `<w>` and ends with `</w>` instead.
+pub struct CharacterTokenizer {
+    words: Box<dyn Tokenizer>,
+    word_boundaries: bool,
+}
+
+impl CharacterTokenizer {
+    /// Split the characters of the words matched by the `[\w']+` regex.
+    pub fn new(word_boundaries: bool) -> CharacterTokenizer {
+        CharacterTokenizer {
+            words: Box::new(RegexTokenizer::default()),
+            word_boundaries,
+        }
+    }
+
+    /// Split the characters of the words split off by this tokenizer instead.
+    pub fn with_word_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.words = Box::new(tokenizer);
+
+        self
+    }
+}
+
+impl Tokenizer for CharacterTokenizer {
+    fn tokenize(&self, line: &str) -> Vec<String> {
+        characters(&self.words.tokenize(line), self.word_boundaries)
+    }
+}
+
+/// The characters of words, separated by a space, or with word boundaries, with every
+/// word wrapped in `<w>` and `</w>`. Masks like `<NUM>` are kept as a single character.
+pub fn characters(words: &[String], word_boundaries: bool) -> Vec<String> {
+    let word_characters = |word: &String| -> Vec<String> {
+        match [NUMBER, URL, EMAIL].contains(&word.as_str()) {
+            true => vec![word.clone()],
+            false => word.chars().map(|c| c.to_string()).collect(),
+        }
+    };
+
+    match word_boundaries {
+        false => words
+            .iter()
+            .enumerate()
+            .flat_map(|(i, word)| {
+                (i > 0)
+                    .then(|| " ".to_string())
+                    .into_iter()
+                    .chain(word_characters(word))
+            })
+            .collect(),
+        true => words
+            .iter()
+            .flat_map(|word| {
+                once(WORD_START.to_string())
+                    .chain(word_characters(word))
+                    .chain(once(WORD_END.to_string()))
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tokenizer::*;
+
+    const LINE: &str = "The well-known café's 3.5 stars, isn't it?";
+
+    #[test]
+    fn test_regex_tokenizer() {
+        let expected = vec![
+            "The", "well", "known", "café's", "3", "5", "stars", "isn't", "it",
+        ];
+
+        assert_eq!(expected, RegexTokenizer::default().tokenize(LINE));
+        assert_eq!(
+            vec!["well-known"],
+            RegexTokenizer::new(r"\w+-\w+").unwrap().tokenize(LINE)
+        );
+    }
+
+    #[test]
+    fn test_whitespace_tokenizer() {
+        let expected = vec![
+            "The",
+            "well-known",
+            "café's",
+            "3.5",
+            "stars,",
+            "isn't",
+            "it?",
+        ];
+
+        assert_eq!(expected, WhitespaceTokenizer.tokenize(LINE));
+    }
+
+    #[test]
+    fn test_unicode_word_tokenizer() {
+        let expected = vec![
+            "The", "well", "known", "café's", "3.5", "stars", "isn't", "it",
+        ];
+
+        assert_eq!(expected, UnicodeWordTokenizer.tokenize(LINE));
+    }
+
+    #[test]
+    fn test_punctuation_tokenizer() {
+        let expected = vec![
+            "The",
+            "well-known",
+            "café's",
+            "3",
+            ".",
+            "5",
+            "stars",
+            ",",
+            "isn't",
+            "it",
+            "?",
+        ];
+
+        assert_eq!(expected, PunctuationTokenizer.tokenize(LINE));
+    }
+
+    #[test]
+    fn test_character_tokenizer() {
+        assert_eq!(
+            vec!["i", "s", " ", "i", "t"],
+            CharacterTokenizer::new(false).tokenize("is it?")
+        );
+        assert_eq!(
+            vec!["<w>", "i", "s", "</w>", "<w>", "i", "t", "?", "</w>"],
+            CharacterTokenizer::new(true)
+                .with_word_tokenizer(WhitespaceTokenizer)
+                .tokenize("is it?")
+        );
+    }
+}
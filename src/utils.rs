@@ -1,27 +1,23 @@
-#![feature(test)]
-
-extern crate test;
-
-use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::hash::Hash;
 
-use itertools::Itertools;
-use regex::Regex;
+pub const SENTENCE_START: &str = "<s>";
+pub const SENTENCE_END: &str = "</s>";
+pub const UNKNOWN: &str = "<UNK>";
+pub const WORD_START: &str = "<w>";
+pub const WORD_END: &str = "</w>";
+pub const NUMBER: &str = "<NUM>";
+pub const URL: &str = "<URL>";
+pub const EMAIL: &str = "<EMAIL>";
 
-pub fn line_to_words(line: &str) -> Vec<String> {
-    lazy_static! {
-        static ref WORDS_RE: Regex = Regex::new(r"([\w']+)").unwrap();
-    }
+pub fn pad_words(words: Vec<String>) -> Vec<String> {
+    let mut padded = Vec::with_capacity(words.len() + 2);
 
-    WORDS_RE
-        .captures_iter(line)
-        .map(|capture| capture[1].to_lowercase())
-        .collect_vec()
-}
+    padded.push(SENTENCE_START.to_string());
+    padded.extend(words);
+    padded.push(SENTENCE_END.to_string());
 
-pub fn lines_to_word_lists(lines: &[String]) -> Vec<Vec<String>> {
-    lines.iter().map(|line| line_to_words(line)).collect_vec()
+    padded
 }
 
 pub fn to_hashmap_keys<K, V, F>(list: &[K], map_key_to_value: F) -> HashMap<K, V>
@@ -63,19 +59,6 @@ where
     target
 }
 
-pub fn count_nested<V>(list_of_lists: &[Vec<V>]) -> HashMap<V, u32>
-where
-    V: Eq + Hash + Clone,
-{
-    list_of_lists
-        .iter()
-        .fold(HashMap::new(), |total_counts: HashMap<V, u32>, words| {
-            let current_counts = count_all(words);
-
-            merge_hashmaps_with(current_counts, total_counts, |l, r| l + r)
-        })
-}
-
 pub fn count_all<V>(list: &[V]) -> HashMap<V, u32>
 where
     V: Eq + Hash + Clone,
@@ -93,29 +76,6 @@ mod tests {
     use itertools::Itertools;
     use std::collections::HashMap;
 
-    #[test]
-    fn test_lines_to_word_lists() {
-        let lines = vec!["chicago is cold", "africa is hot"]
-            .iter()
-            .map(|s| s.to_string())
-            .collect_vec();
-
-        let expected = vec![vec!["chicago", "is", "cold"], vec!["africa", "is", "hot"]];
-        let actual = lines_to_word_lists(&lines);
-
-        assert_eq!(actual, expected);
-    }
-
-    #[bench]
-    fn bench_lines_to_word_lists(b: &mut test::Bencher) {
-        let lines = vec!["chicago is cold", "africa is hot"]
-            .iter()
-            .map(|s| s.to_string())
-            .collect_vec();
-
-        b.iter(|| lines_to_word_lists(&lines))
-    }
-
     #[test]
     fn test_merge_hashmaps_with() {
         let mut left = HashMap::new();
@@ -141,7 +101,7 @@ mod tests {
 
     #[test]
     fn test_count_all_words() {
-        let word_list: Vec<String> = (vec!["chicago", "is", "cold", "africa", "is", "hot"])
+        let word_list: Vec<String> = (["chicago", "is", "cold", "africa", "is", "hot"])
             .iter()
             .map(|word| word.to_string())
             .collect();
@@ -160,7 +120,7 @@ mod tests {
 
     #[test]
     fn test_count_all_bigrams() {
-        let word_list: Vec<(String, String)> = (vec!["chicago", "is", "cold", "is", "cold"])
+        let word_list: Vec<(String, String)> = (["chicago", "is", "cold", "is", "cold"])
             .iter()
             .map(|word| word.to_string())
             .tuple_windows()
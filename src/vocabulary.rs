@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::utils::{SENTENCE_END, SENTENCE_START, UNKNOWN};
+
+/// The ID a word is stored as in the n-gram counts.
+pub type WordId = u32;
+
+pub const SENTENCE_START_ID: WordId = 0;
+pub const SENTENCE_END_ID: WordId = 1;
+// every word outside of the vocabulary, whether it is closed or not
+pub const UNKNOWN_ID: WordId = 2;
+
+/// Maps the words of a corpus to integer IDs and back, so that n-grams can be
+/// stored as arrays of IDs and words are only kept once.
+///
+/// The sentence markers and `<UNK>` always have the same IDs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vocabulary {
+    // both directions point to the same string of a word, which is an Arc
+    // so that the vocabularies of counting threads can be sent back
+    words: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, WordId>,
+}
+
+impl Default for Vocabulary {
+    fn default() -> Self {
+        let mut vocabulary = Vocabulary {
+            words: vec![],
+            ids: HashMap::new(),
+        };
+
+        for word in [SENTENCE_START, SENTENCE_END, UNKNOWN] {
+            vocabulary.intern(word);
+        }
+
+        vocabulary
+    }
+}
+
+impl Vocabulary {
+    pub fn new() -> Vocabulary {
+        Default::default()
+    }
+
+    /// The ID of the word, which is added to the vocabulary when it is new.
+    pub fn intern(&mut self, word: &str) -> WordId {
+        match self.ids.get(word) {
+            Some(id) => *id,
+            None => {
+                let id = self.words.len() as WordId;
+                let word: Arc<str> = Arc::from(word);
+
+                self.words.push(word.clone());
+                self.ids.insert(word, id);
+
+                id
+            }
+        }
+    }
+
+    /// Add the words of another vocabulary, and give back
+    /// the ID in this vocabulary of every ID in the other one.
+    pub fn merge(&mut self, other: &Vocabulary) -> Vec<WordId> {
+        other.words.iter().map(|word| self.intern(word)).collect()
+    }
+
+    pub fn id(&self, word: &str) -> Option<WordId> {
+        self.ids.get(word).copied()
+    }
+
+    /// The ID of every word, where words outside of the vocabulary are `<UNK>`.
+    pub fn ids(&self, words: &[String]) -> Vec<WordId> {
+        words
+            .iter()
+            .map(|word| self.id(word).unwrap_or(UNKNOWN_ID))
+            .collect()
+    }
+
+    /// Panics if the ID is not part of the vocabulary.
+    pub fn word(&self, id: WordId) -> &str {
+        &self.words[id as usize]
+    }
+
+    pub fn words(&self, ids: &[WordId]) -> Vec<String> {
+        ids.iter().map(|id| self.word(*id).to_string()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::UNKNOWN;
+    use crate::vocabulary::*;
+
+    #[test]
+    fn test_intern() {
+        let mut vocabulary = Vocabulary::new();
+
+        let chicago = vocabulary.intern("chicago");
+        let is = vocabulary.intern("is");
+
+        assert_eq!(chicago, vocabulary.intern("chicago"));
+        assert_ne!(chicago, is);
+        assert_eq!(5, vocabulary.len());
+        assert_eq!("chicago", vocabulary.word(chicago));
+        assert_eq!(Some(SENTENCE_START_ID), vocabulary.id("<s>"));
+
+        // the word is stored once, for both directions
+        let (word, _id) = vocabulary.ids.get_key_value("chicago").unwrap();
+        assert!(Arc::ptr_eq(word, &vocabulary.words[chicago as usize]));
+    }
+
+    #[test]
+    fn test_unknown_words() {
+        let mut vocabulary = Vocabulary::new();
+        vocabulary.intern("chicago");
+
+        let words = vec!["chicago".to_string(), "africa".to_string()];
+        let ids = vocabulary.ids(&words);
+
+        assert_eq!(UNKNOWN_ID, ids[1]);
+        assert_eq!(vec!["chicago", UNKNOWN], vocabulary.words(&ids));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut vocabulary = Vocabulary::new();
+        vocabulary.intern("chicago");
+
+        let mut other = Vocabulary::new();
+        let hot = other.intern("hot");
+        let chicago = other.intern("chicago");
+
+        let other_to_vocabulary = vocabulary.merge(&other);
+
+        assert_eq!(
+            Some(other_to_vocabulary[chicago as usize]),
+            vocabulary.id("chicago")
+        );
+        assert_eq!(
+            Some(other_to_vocabulary[hot as usize]),
+            vocabulary.id("hot")
+        );
+        assert_eq!(
+            SENTENCE_END_ID,
+            other_to_vocabulary[SENTENCE_END_ID as usize]
+        );
+    }
+}